@@ -0,0 +1,247 @@
+use crate::evaluator::Evaluator;
+use crate::BinOp;
+use crate::RuntimeError;
+use crate::Value;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, Signed};
+use std::cmp::Ordering;
+
+/// 組み込み関数の実装。演算の設定 (オーバーフローの扱いなど) を参照できるよう Evaluator を受け取る
+pub type Builtin = fn(&Evaluator, &[Value]) -> Result<Value, RuntimeError>;
+
+// 名前、引数の数、実装
+const BUILTINS: &[(&str, usize, Builtin)] = &[
+    ("abs", 1, abs),
+    ("min", 2, min),
+    ("max", 2, max),
+    ("pow", 2, pow),
+    ("sqrt", 1, sqrt),
+    ("floor", 1, floor),
+    ("ceil", 1, ceil),
+    ("round", 1, round),
+    ("gcd", 2, gcd),
+    ("lcm", 2, lcm),
+    ("sin", 1, |_, args| Ok(Value::Float(real(&args[0])?.sin()))),
+    ("cos", 1, |_, args| Ok(Value::Float(real(&args[0])?.cos()))),
+    ("tan", 1, |_, args| Ok(Value::Float(real(&args[0])?.tan()))),
+    ("asin", 1, asin),
+    ("acos", 1, acos),
+    ("atan", 1, |_, args| Ok(Value::Float(real(&args[0])?.atan()))),
+    ("atan2", 2, |_, args| Ok(Value::Float(real(&args[0])?.atan2(real(&args[1])?)))),
+    ("exp", 1, |_, args| Ok(Value::Float(real(&args[0])?.exp()))),
+    ("ln", 1, ln),
+    ("log", 1, log),
+    ("log2", 1, log2),
+    ("re", 1, re),
+    ("im", 1, im),
+    ("arg", 1, arg),
+    ("conj", 1, conj),
+];
+
+// 名前と値
+const CONSTANTS: &[(&str, f64)] = &[("pi", std::f64::consts::PI), ("e", std::f64::consts::E)];
+
+/// 名前から組み込み関数を探す
+pub fn lookup(id: &str) -> Option<(usize, Builtin)> {
+    BUILTINS
+        .iter()
+        .find(|(name, _, _)| *name == id)
+        .map(|(_, arity, f)| (*arity, *f))
+}
+
+/// 名前から組み込み定数を探す
+pub fn constant(id: &str) -> Option<Value> {
+    CONSTANTS
+        .iter()
+        .find(|(name, _)| *name == id)
+        .map(|(_, value)| Value::Float(*value))
+}
+
+/// 引数の数を確かめてから組み込み関数を呼び出す
+pub fn call(ev: &Evaluator, id: &str, arity: usize, f: Builtin, args: &[Value]) -> Result<Value, RuntimeError> {
+    if args.len() != arity {
+        return Err(RuntimeError::Arity {
+            id: id.to_string(),
+            expected: arity,
+            found: args.len(),
+        });
+    }
+    f(ev, args)
+}
+
+// 実数として取り出す
+fn real(v: &Value) -> Result<f64, RuntimeError> {
+    match v {
+        Value::Complex { .. } => Err(RuntimeError::Unsupported("複素数を引数に取れない関数".to_string())),
+        Value::Unit => Err(RuntimeError::Unsupported("値のない式を引数に取れない関数".to_string())),
+        v => Ok(v.to_f64()),
+    }
+}
+
+// 整数として取り出す
+fn integer(v: &Value) -> Result<BigInt, RuntimeError> {
+    match v {
+        Value::Int(_) | Value::Big(_) => Ok(v.to_bigint()),
+        _ => Err(RuntimeError::Unsupported("整数以外を引数に取れない関数".to_string())),
+    }
+}
+
+// 実数の大小を比べる。浮動小数点数が含まれなければ正確に比べる
+fn compare(a: &Value, b: &Value) -> Result<Ordering, RuntimeError> {
+    if matches!(a, Value::Float(_)) || matches!(b, Value::Float(_)) {
+        return real(a)?.partial_cmp(&real(b)?).ok_or(RuntimeError::Unsupported("NaN の比較".to_string()));
+    }
+    real(a)?;
+    real(b)?;
+    Ok(a.to_rational().cmp(&b.to_rational()))
+}
+
+// 実数を浮動小数点数なら f、それ以外なら r で整数に丸める
+fn to_integer(ev: &Evaluator, v: &Value, f: fn(f64) -> f64, r: fn(&BigRational) -> BigRational) -> Result<Value, RuntimeError> {
+    match v {
+        Value::Int(_) | Value::Big(_) => Ok(v.clone()),
+        Value::Float(x) => match BigInt::from_f64(f(*x)) {
+            Some(n) => ev.fit(n),
+            None => Err(RuntimeError::Unsupported("有限でない数の丸め".to_string())),
+        },
+        Value::Complex { .. } => Err(RuntimeError::Unsupported("複素数の丸め".to_string())),
+        _ => ev.fit(r(&v.to_rational()).to_integer()),
+    }
+}
+
+// 定義域を確かめる
+fn domain(id: &str, ok: bool) -> Result<(), RuntimeError> {
+    if ok {
+        Ok(())
+    } else {
+        Err(RuntimeError::Domain(id.to_string()))
+    }
+}
+
+// 絶対値。実数は型を保ったまま、複素数は大きさを返す
+fn abs(ev: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        // abs(i32::MIN) は i32 に収まらないのでオーバーフローの扱いに従う
+        Value::Int(_) | Value::Big(_) => ev.fit(args[0].to_bigint().abs()),
+        Value::Rational(r) => Ok(Value::Rational(r.abs())),
+        Value::Decimal { unscaled, scale } => Ok(Value::Decimal { unscaled: unscaled.abs(), scale: *scale }),
+        Value::Float(f) => Ok(Value::Float(f.abs())),
+        Value::Complex { re, im } => Ok(Value::Float(re.hypot(*im))),
+        Value::Unit => Err(RuntimeError::Unsupported("値のない式の絶対値".to_string())),
+    }
+}
+
+fn min(_: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    match compare(&args[0], &args[1])? {
+        Ordering::Greater => Ok(args[1].clone()),
+        _ => Ok(args[0].clone()),
+    }
+}
+
+fn max(_: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    match compare(&args[0], &args[1])? {
+        Ordering::Less => Ok(args[1].clone()),
+        _ => Ok(args[0].clone()),
+    }
+}
+
+// ^ 演算子と同じ
+fn pow(ev: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    ev.binary(BinOp::Pow, args[0].clone(), args[1].clone())
+}
+
+// 平方根。負の数や複素数は主値の複素数を返す
+fn sqrt(_: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (re, im) = args[0].to_complex();
+    if im == 0.0 && re >= 0.0 {
+        return Ok(Value::Float(re.sqrt()));
+    }
+    let r = re.hypot(im);
+    let sign = if im < 0.0 { -1.0 } else { 1.0 };
+    Ok(Value::from_complex(((r + re) / 2.0).sqrt(), sign * ((r - re) / 2.0).sqrt()))
+}
+
+fn floor(ev: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    to_integer(ev, &args[0], f64::floor, |r| r.floor())
+}
+
+fn ceil(ev: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    to_integer(ev, &args[0], f64::ceil, |r| r.ceil())
+}
+
+// 0 から遠い方に丸める
+fn round(ev: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    to_integer(ev, &args[0], f64::round, |r| r.round())
+}
+
+fn gcd(ev: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    ev.fit(integer(&args[0])?.gcd(&integer(&args[1])?))
+}
+
+fn lcm(ev: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    ev.fit(integer(&args[0])?.lcm(&integer(&args[1])?))
+}
+
+fn asin(_: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    let x = real(&args[0])?;
+    domain("asin", (-1.0..=1.0).contains(&x))?;
+    Ok(Value::Float(x.asin()))
+}
+
+fn acos(_: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    let x = real(&args[0])?;
+    domain("acos", (-1.0..=1.0).contains(&x))?;
+    Ok(Value::Float(x.acos()))
+}
+
+// 自然対数
+fn ln(_: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    let x = real(&args[0])?;
+    domain("ln", x > 0.0)?;
+    Ok(Value::Float(x.ln()))
+}
+
+// 常用対数
+fn log(_: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    let x = real(&args[0])?;
+    domain("log", x > 0.0)?;
+    Ok(Value::Float(x.log10()))
+}
+
+fn log2(_: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    let x = real(&args[0])?;
+    domain("log2", x > 0.0)?;
+    Ok(Value::Float(x.log2()))
+}
+
+// 実部
+fn re(_: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        Value::Complex { re, .. } => Ok(Value::Float(*re)),
+        v => Ok(v.clone()),
+    }
+}
+
+// 虚部
+fn im(_: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        Value::Complex { im, .. } => Ok(Value::Float(*im)),
+        _ => Ok(Value::Int(0)),
+    }
+}
+
+// 偏角
+fn arg(_: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    let (re, im) = args[0].to_complex();
+    Ok(Value::Float(im.atan2(re)))
+}
+
+// 共役複素数
+fn conj(_: &Evaluator, args: &[Value]) -> Result<Value, RuntimeError> {
+    match &args[0] {
+        Value::Complex { re, im } => Ok(Value::Complex { re: *re, im: -im }),
+        v => Ok(v.clone()),
+    }
+}
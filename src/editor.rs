@@ -0,0 +1,311 @@
+// REPL の 1 行の読み込み
+// 端末では rustyline で行編集 (カーソル移動、上下キーの履歴、Ctrl-R の逆方向検索、Tab での補完、色付け) を行い、
+// パイプなどからはそのまま 1 行ずつ読む
+
+use calculator::scanner;
+use calculator::Declaration;
+use calculator::Interpreter;
+use calculator::ParseError;
+use calculator::Token;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::{CmdKind, Highlighter};
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{ColorMode, Config, Context, Editor, Helper};
+use std::borrow::Cow;
+use std::io::{self, IsTerminal};
+use std::ops::Range;
+use std::path::PathBuf;
+
+// 履歴ファイルの名前 (ホームディレクトリに置く)
+const HISTORY_FILE: &str = ".calculator_history";
+// 履歴ファイルに残す行数
+const HISTORY_SIZE: usize = 1000;
+
+// 色付けに使う SGR の番号
+const KEYWORD_COLOR: &str = "1;35";
+const NUMBER_COLOR: &str = "36";
+const IDENT_COLOR: &str = "33";
+const OPERATOR_COLOR: &str = "34";
+// 読めない文字と対応の取れない括弧
+const ERROR_COLOR: &str = "1;31";
+
+/// 1 行読んだ結果
+pub enum Input {
+    Line(String),
+    // 入力中に Ctrl-C が押された
+    Interrupted,
+    // 入力の終わり (Ctrl-D)
+    Eof,
+}
+
+// Tab で予約語、変数名、関数名を補完する
+#[derive(Default)]
+pub struct ReplHelper {
+    // 補完候補。display は一覧での表示、replacement は入力される文字列
+    candidates: Vec<Pair>,
+}
+
+impl ReplHelper {
+    // 予約語と今の変数、関数を補完候補にする
+    fn set_candidates(&mut self, interpreter: &Interpreter) {
+        let keywords = scanner::KEYWORDS.iter().map(|(word, _)| candidate(word.to_string(), word.to_string()));
+        let vars = interpreter.env().keys().map(|name| candidate(name.clone(), name.clone()));
+        let funcs = interpreter.functions().iter().map(|(name, declaration)| match declaration {
+            Declaration::Function { params, .. } => candidate(format!("{}({})", name, params.join(", ")), format!("{}(", name)),
+            Declaration::Native { arity, .. } => candidate(format!("{}/{}", name, arity), format!("{}(", name)),
+        });
+        self.candidates = keywords.chain(vars).chain(funcs).collect();
+        self.candidates.sort_by(|a, b| a.display.cmp(&b.display));
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        // カーソルの直前の識別子 (英小文字、_、数字の並び。先頭の数字は数値なので除く) を補完する
+        let start = line[..pos].trim_end_matches(|c: char| c.is_ascii_lowercase() || c == '_' || c.is_ascii_digit()).len();
+        let start = pos - line[start..pos].trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let word = &line[start..pos];
+        if word.is_empty() {
+            return Ok((pos, vec![]));
+        }
+        let candidates = self
+            .candidates
+            .iter()
+            .filter(|candidate| candidate.replacement.starts_with(word))
+            .map(|candidate| Pair {
+                display: candidate.display.clone(),
+                replacement: candidate.replacement.clone(),
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+// 入力中の行を scanner のトークンごとに色付けする
+impl Highlighter for ReplHelper {
+    fn highlight<'l>(&self, line: &'l str, _: usize) -> Cow<'l, str> {
+        let spans = scanner::spans(line);
+        let unmatched = unmatched_brackets(&spans);
+        let mut result = String::new();
+        let mut last = 0;
+        for (i, (token, range)) in spans.iter().enumerate() {
+            result.push_str(&line[last..range.start]);
+            let color = if unmatched.contains(&i) { Some(ERROR_COLOR) } else { color(token) };
+            match color {
+                Some(color) => result.push_str(&format!("\x1b[{}m{}\x1b[0m", color, &line[range.clone()])),
+                None => result.push_str(&line[range.clone()]),
+            }
+            last = range.end;
+        }
+        result.push_str(&line[last..]);
+        Cow::Owned(result)
+    }
+
+    // 文字の入力や削除のたびに色付けし直す。カーソルの移動では色は変わらない
+    fn highlight_char(&self, _: &str, _: usize, kind: CmdKind) -> bool {
+        kind != CmdKind::MoveCursor
+    }
+}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+pub enum LineReader {
+    Editor {
+        editor: Box<Editor<ReplHelper, FileHistory>>,
+        // 履歴ファイル。ホームディレクトリが分からなければ保存しない
+        history: Option<PathBuf>,
+    },
+    Stdin,
+}
+
+impl LineReader {
+    /// 標準入力が端末なら行編集を使い、そうでなければ標準入力から直接読む
+    pub fn new() -> Self {
+        if !io::stdin().is_terminal() {
+            return LineReader::Stdin;
+        }
+        let config = Config::builder()
+            .max_history_size(HISTORY_SIZE)
+            .map(|builder| builder.color_mode(color_mode()).build());
+        let mut editor = match config.and_then(Editor::with_config) {
+            Ok(editor) => editor,
+            Err(_) => return LineReader::Stdin,
+        };
+        editor.set_helper(Some(ReplHelper::default()));
+        let mut reader = LineReader::Editor {
+            editor: Box::new(editor),
+            history: history_path(),
+        };
+        if let LineReader::Editor { editor, history: Some(path) } = &mut reader {
+            // 初回は履歴ファイルが無いので読めなくてもよい
+            let _ = editor.load_history(path);
+        }
+        reader
+    }
+
+    /// prompt を表示して 1 行読む。返す行の末尾に改行は含まない
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Input> {
+        match self {
+            LineReader::Editor { editor, .. } => match editor.readline(prompt) {
+                Ok(line) => Ok(Input::Line(line)),
+                Err(ReadlineError::Interrupted) => Ok(Input::Interrupted),
+                Err(ReadlineError::Eof) => Ok(Input::Eof),
+                Err(ReadlineError::Io(e)) => Err(e),
+                Err(e) => Err(io::Error::other(e)),
+            },
+            LineReader::Stdin => {
+                let mut line = String::new();
+                match io::stdin().read_line(&mut line)? {
+                    0 => Ok(Input::Eof),
+                    _ => Ok(Input::Line(line.trim_end_matches(['\n', '\r']).to_string())),
+                }
+            }
+        }
+    }
+
+    /// 補完候補を今の変数と関数に合わせる
+    /// 関数は仮引数の並びも一覧に表示する
+    pub fn update_completion(&mut self, interpreter: &Interpreter) {
+        let LineReader::Editor { editor, .. } = self else {
+            return;
+        };
+        if let Some(helper) = editor.helper_mut() {
+            helper.set_candidates(interpreter);
+        }
+    }
+
+    /// 端末から読んでいるか
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, LineReader::Editor { .. })
+    }
+
+    /// 入力を履歴に加える。複数行の文は 1 行にまとめる
+    pub fn add_history(&mut self, entry: &str) {
+        if let LineReader::Editor { editor, .. } = self {
+            let entry: Vec<&str> = entry.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+            let _ = editor.add_history_entry(entry.join(" "));
+        }
+    }
+
+    /// 履歴をファイルに書き出す
+    pub fn save_history(&mut self) {
+        if let LineReader::Editor { editor, history: Some(path) } = self {
+            if let Err(e) = editor.save_history(path) {
+                eprint!("履歴を {} に保存できない：{}\n", path.display(), e);
+            }
+        }
+    }
+}
+
+// トークンの種類ごとの色
+fn color(token: &Result<Token, ParseError>) -> Option<&'static str> {
+    match token {
+        Ok(Token::IF | Token::ELSE | Token::FN | Token::RETURN | Token::XOR) => Some(KEYWORD_COLOR),
+        Ok(Token::NUMBER(_)) => Some(NUMBER_COLOR),
+        Ok(Token::IDENT(_)) => Some(IDENT_COLOR),
+        Ok(Token::LPAR | Token::RPAR | Token::LBRACE | Token::RBRACE | Token::COMMA | Token::SEMICOLON) => None,
+        Ok(_) => Some(OPERATOR_COLOR),
+        Err(_) => Some(ERROR_COLOR),
+    }
+}
+
+// 対応の取れない括弧が spans の何番目にあるか
+fn unmatched_brackets(spans: &[(Result<Token, ParseError>, Range<usize>)]) -> Vec<usize> {
+    let mut unmatched = vec![];
+    // 閉じていない開き括弧の位置
+    let mut open: Vec<usize> = vec![];
+    for (i, (token, _)) in spans.iter().enumerate() {
+        let opening = match token {
+            Ok(Token::LPAR) | Ok(Token::LBRACE) => {
+                open.push(i);
+                continue;
+            }
+            Ok(Token::RPAR) => Token::LPAR,
+            Ok(Token::RBRACE) => Token::LBRACE,
+            _ => continue,
+        };
+        match open.last() {
+            Some(j) if spans[*j].0 == Ok(opening) => {
+                open.pop();
+            }
+            _ => unmatched.push(i),
+        }
+    }
+    unmatched.extend(open);
+    unmatched
+}
+
+// NO_COLOR が設定されているか TERM=dumb なら色を付けない
+// それ以外は rustyline が端末の対応を調べる
+fn color_mode() -> ColorMode {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let dumb = std::env::var_os("TERM").is_some_and(|v| v == "dumb");
+    if no_color || dumb {
+        ColorMode::Disabled
+    } else {
+        ColorMode::Enabled
+    }
+}
+
+fn candidate(display: String, replacement: String) -> Pair {
+    Pair { display, replacement }
+}
+
+// ホームディレクトリの履歴ファイル
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustyline::history::DefaultHistory;
+
+    // line の末尾で補完したときの開始位置と、(display, replacement) の一覧
+    fn complete(helper: &ReplHelper, line: &str) -> (usize, Vec<(String, String)>) {
+        let history = DefaultHistory::new();
+        let (start, pairs) = helper.complete(line, line.len(), &Context::new(&history)).unwrap();
+        (start, pairs.into_iter().map(|pair| (pair.display, pair.replacement)).collect())
+    }
+
+    #[test]
+    fn test_complete() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_str("rate = 150 ; ratio = 2 ; round2(x, n) = x ; x = 1").unwrap();
+        let mut helper = ReplHelper::default();
+        helper.set_candidates(&interpreter);
+
+        // 入力中の識別子で始まる候補だけが選ばれ、関数は name( で置き換えられること
+        let (start, pairs) = complete(&helper, "y = ra");
+        assert_eq!(start, 4);
+        assert_eq!(pairs, vec![("rate".to_string(), "rate".to_string()), ("ratio".to_string(), "ratio".to_string())]);
+        let (start, pairs) = complete(&helper, "rou");
+        assert_eq!(start, 0);
+        assert_eq!(pairs, vec![("round2(x, n)".to_string(), "round2(".to_string())]);
+
+        // 先頭の数字は数値として飛ばして補完すること
+        let (start, pairs) = complete(&helper, "2rat");
+        assert_eq!(start, 1);
+        assert_eq!(pairs.len(), 2);
+        // 予約語も補完されること
+        let (_, pairs) = complete(&helper, "ret");
+        assert_eq!(pairs, vec![("return".to_string(), "return".to_string())]);
+
+        // 識別子を入力していなければ候補を出さないこと
+        assert_eq!(complete(&helper, "1 + "), (4, vec![]));
+        assert_eq!(complete(&helper, "12"), (2, vec![]));
+    }
+}
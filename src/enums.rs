@@ -1,5 +1,9 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
 use std::collections::HashMap;
-pub type Env = HashMap<String, i32>;
+use std::fmt;
+use std::sync::Arc;
+pub type Env = HashMap<String, Value>;
 pub type FunctionTable = HashMap<String, Declaration>;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -14,21 +18,80 @@ pub enum Token {
     RBRACE,
     COMMA,
     EQ,
-    NUMBER(i32),
+    NUMBER(Value),
     IF,
     ELSE,
     IDENT(String),
     SEMICOLON,
+    PERCENT,
+    CARET,
+    AMP,
+    PIPE,
+    XOR,
+    TILDE,
+    SHL,
+    SHR,
+    BANG,
     FN, 
     RETURN,
 }
 
+/// 計算結果の値
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Int(i32),
+    // i32 に収まらない整数。i32 に収まる値は常に Int で持つ
+    Big(BigInt),
+    // 既約分数。分母は正で 1 より大きい
+    Rational(BigRational),
+    // 固定小数点数。値は unscaled / 10^scale
+    Decimal {
+        unscaled: BigInt,
+        scale: u32,
+    },
+    // 浮動小数点数。sqrt などの結果として現れる
+    Float(f64),
+    // 複素数。虚部が 0 になった場合は Float にする
+    Complex {
+        re: f64,
+        im: f64,
+    },
+    // 値を持たない文 (関数定義など) の結果
+    Unit,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Declaration {
     Function {
         params: Vec<String>,
         body: Box<Statement>,
     },
+    // 組み込み先のプログラムが登録した関数
+    Native {
+        arity: usize,
+        func: NativeFn,
+    },
+}
+
+/// 組み込み先のプログラムが登録する関数の本体
+pub type NativeFnBody = dyn Fn(&[Value]) -> Result<Value, RuntimeError> + Send + Sync;
+
+/// 関数テーブルに入れられるようにクロージャを包んだもの
+/// 比較は同じクロージャを指しているかどうかで行う
+/// Interpreter ごと別スレッドに渡せるように Send + Sync にする
+#[derive(Clone)]
+pub struct NativeFn(pub Arc<NativeFnBody>);
+
+impl fmt::Debug for NativeFn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NativeFn")
+    }
+}
+
+impl PartialEq for NativeFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -37,6 +100,26 @@ pub enum BinOp {
     Sub,
     Mul,
     Div,
+    // 剰余。符号は割られる数に合わせる (Div の切り捨てと対になる)
+    Rem,
+    // べき乗
+    Pow,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    // 算術右シフト
+    Shr,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum UnOp {
+    Neg,
+    BitNot,
+    // 後置の階乗 (5!)
+    Factorial,
+    // 後置のパーセント (50% = 0.50)
+    Percent,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -46,7 +129,11 @@ pub enum Expr {
         lhs: Box<Expr>,
         rhs: Box<Expr>,
     },
-    Number(i32),
+    Unary {
+        op: UnOp,
+        expr: Box<Expr>,
+    },
+    Number(Value),
     Var(String),
     FunctionCall {
         id: String,
@@ -63,18 +150,11 @@ pub enum Statement {
         st1: Box<Statement>,
         st2: Box<Statement>,
     },
-    Print {
-        // TODO 関数作ったら関数にする
-        expr: Expr,
-    },
     FunctionDefine {
         id: String,
         params: Vec<String>,
         body: Box<Statement>,
     },
-    FunctionCall {
-        expr: Expr,
-    },
     Return {
         expr: Box<Expr>,
     },
@@ -94,3 +174,118 @@ pub enum Statement {
 pub enum Syntax {
     Statement(Statement),
 }
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum RuntimeError {
+    // 実行ステップ数の上限に達した
+    OutOfFuel,
+    // CancelToken で中断された
+    Interrupted,
+    // 整数演算がオーバーフローした
+    Overflow,
+    // 0 で割った
+    DivisionByZero,
+    // 値の種類に対して使えない演算
+    Unsupported(String),
+    // シフト量が 0 以上 32 未満でない
+    ShiftOutOfRange,
+    // 組み込み関数の定義域の外の値が渡された
+    Domain(String),
+    // 組み込み関数と同じ名前の関数を定義しようとした
+    BuiltinRedefinition(String),
+    // 組み込み先のプログラムが登録した関数が失敗した
+    Native(String),
+    // 代入されていない変数を参照した
+    UndefinedVariable(String),
+    // 定義されていない関数を呼び出した
+    UndefinedFunction(String),
+    // 関数が return せずに終わった
+    MissingReturn(String),
+    // 関数の引数の数が合わない
+    Arity {
+        id: String,
+        expected: usize,
+        found: usize,
+    },
+}
+
+/// 字句解析・構文解析のエラー
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseError {
+    // トークンにならない文字
+    InvalidCharacter(char),
+    // 数値として読めないリテラル
+    InvalidNumber(String),
+    // expected のトークンが来るべき位置に found があった。None は入力の終わり
+    Expected {
+        expected: Token,
+        found: Option<Token>,
+    },
+    // 文や式を始められない (または文の後に余分な) トークン。None は入力の終わり
+    Unexpected(Option<Token>),
+}
+
+/// 文字列を評価したときのエラー
+#[derive(Debug, PartialEq, Clone)]
+pub enum Error {
+    Parse(ParseError),
+    Runtime(RuntimeError),
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(e: RuntimeError) -> Self {
+        Error::Runtime(e)
+    }
+}
+
+/// 整数演算がオーバーフローしたときの扱い
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Overflow {
+    // 多倍長整数に昇格する
+    #[default]
+    Promote,
+    // RuntimeError::Overflow にする
+    Error,
+    // 2 の補数で折り返す
+    Wrapping,
+    // i32 の最大値・最小値に張り付かせる
+    Saturating,
+}
+
+/// 整数同士の割り算の扱い
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Division {
+    // 0 方向に切り捨てる
+    #[default]
+    Truncate,
+    // 割り切れなければ有理数にする
+    Exact,
+}
+
+/// 固定小数点数の丸め方
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Rounding {
+    // 最近接偶数への丸め（銀行丸め）
+    #[default]
+    HalfEven,
+    // 四捨五入
+    HalfUp,
+    // 切り捨て
+    Truncate,
+}
+
+/// 組み込み関数と同じ名前の関数を定義したときの扱い
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum BuiltinPolicy {
+    // 組み込み関数を優先し、同じ名前の定義は RuntimeError::BuiltinRedefinition にする
+    #[default]
+    Protected,
+    // 定義された関数で組み込み関数を上書きする
+    Overridable,
+}
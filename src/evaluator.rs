@@ -1,92 +1,605 @@
+use crate::builtins;
 use crate::BinOp;
+use crate::BuiltinPolicy;
 use crate::Env;
 use crate::FunctionTable;
 use crate::Declaration;
+use crate::Division;
 use crate::Expr;
+use crate::NativeFn;
+use crate::Overflow;
+use crate::Rounding;
+use crate::RuntimeError;
 use crate::Statement;
 use crate::Syntax;
+use crate::UnOp;
+use crate::Value;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive, Zero};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
-pub fn eval(syntax: Syntax, env: &mut Env, ft: &mut FunctionTable) -> () {
-    match syntax {
-        Syntax::Statement(st) => {
-            exec(st, env, ft);
-        }
+/// 式文の値を受け取る出力先
+pub type Output = dyn FnMut(&Value) + Send;
+
+// 累乗の結果として計算する整数の最大ビット数
+const MAX_POWER_BITS: u64 = 1 << 20;
+
+// 文を実行した後の制御の流れ
+enum Flow {
+    // 次の文へ進む
+    Normal,
+    // return で値が確定した
+    Return(Value),
+    // 末尾位置（return の式）での関数呼び出し。呼び出し元のフレームを再利用する
+    TailCall { id: String, args: Vec<Value> },
+}
+
+/// 実行中の評価を外部（別スレッドやシグナルハンドラ）から中断するためのトークン
+/// clone したものは同じ状態を共有する
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken(Arc::new(AtomicBool::new(false)))
+    }
+
+    /// 評価の中断を要求する
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// 中断要求を取り消して次の評価に備える
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+pub struct Evaluator {
+    // 残りの実行ステップ数。None なら無制限
+    fuel: Option<u64>,
+    // 中断要求を確認するトークン
+    cancel: Option<CancelToken>,
+    // 整数演算がオーバーフローしたときの扱い
+    overflow: Overflow,
+    // 整数同士の割り算の扱い
+    division: Division,
+    // 固定小数点数の計算結果の最小の小数点以下桁数
+    decimal_scale: u32,
+    // 固定小数点数の丸め方
+    rounding: Rounding,
+    // 組み込み関数と同じ名前の関数を定義したときの扱い
+    builtin_policy: BuiltinPolicy,
+    // 式文の値を受け取る出力先。None なら何もしない
+    output: Option<Box<Output>>,
+    // 最後に実行した式文・代入文の値
+    last: Value,
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-// 文を実行する
-fn exec(statement: Statement, env: &mut Env, ft: &mut FunctionTable) -> () {
-    match statement {
-        Statement::ExprStatement { expr } => {
-            println!("{:?\n}", calc(expr, env, ft));  // TODO 出力の仕方を現状に合わせてるだけなので自然な形にする
+impl Evaluator {
+    pub fn new() -> Self {
+        Evaluator {
+            fuel: None,
+            cancel: None,
+            overflow: Overflow::default(),
+            division: Division::default(),
+            decimal_scale: 2,
+            rounding: Rounding::default(),
+            builtin_policy: BuiltinPolicy::default(),
+            output: None,
+            last: Value::Unit,
         }
-        Statement::CompoundStatement { st1, st2 } => {
-            exec(*st1, env, ft);
-            exec(*st2, env, ft);
+    }
+
+    /// exec / calc の実行回数の上限を指定して作る
+    pub fn with_fuel(fuel: u64) -> Self {
+        Evaluator { fuel: Some(fuel), ..Self::new() }
+    }
+
+    /// 残りの実行ステップ数
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// 残りの実行ステップ数を設定する。None なら無制限
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel = fuel;
+    }
+
+    /// 実行中に中断要求を確認するトークンを設定する
+    pub fn set_cancel_token(&mut self, token: CancelToken) {
+        self.cancel = Some(token);
+    }
+
+    /// 整数演算がオーバーフローしたときの扱いを設定する
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.overflow = overflow;
+    }
+
+    /// 整数同士の割り算の扱いを設定する
+    pub fn set_division(&mut self, division: Division) {
+        self.division = division;
+    }
+
+    /// 固定小数点数の計算結果の最小の小数点以下桁数を設定する
+    pub fn set_decimal_scale(&mut self, scale: u32) {
+        self.decimal_scale = scale;
+    }
+
+    /// 固定小数点数の丸め方を設定する
+    pub fn set_rounding(&mut self, rounding: Rounding) {
+        self.rounding = rounding;
+    }
+
+    /// 組み込み関数と同じ名前の関数を定義したときの扱いを設定する
+    pub fn set_builtin_policy(&mut self, policy: BuiltinPolicy) {
+        self.builtin_policy = policy;
+    }
+
+    /// 式文の値を受け取る出力先を設定する (REPL なら値を表示する)
+    pub fn set_output<F>(&mut self, output: F)
+    where
+        F: FnMut(&Value) + Send + 'static,
+    {
+        self.output = Some(Box::new(output));
+    }
+
+    /// 組み込み先のプログラムの関数を関数テーブルに登録する
+    /// 登録した関数は定義した関数と同じように式から呼び出せる
+    /// Protected では組み込み関数と同じ名前は定義と同じく RuntimeError::BuiltinRedefinition にする
+    pub fn register_native<F>(&self, ft: &mut FunctionTable, id: &str, arity: usize, func: F) -> Result<(), RuntimeError>
+    where
+        F: Fn(&[Value]) -> Result<Value, RuntimeError> + Send + Sync + 'static,
+    {
+        if self.builtin_policy == BuiltinPolicy::Protected && builtins::lookup(id).is_some() {
+            return Err(RuntimeError::BuiltinRedefinition(id.to_string()));
         }
-        // TODO 関数作ったら消す
-        Statement::Print { expr } => {
-            print!("{:?}\n", calc(expr, env, ft));
+        ft.insert(id.to_string(), Declaration::Native { arity, func: NativeFn(Arc::new(func)) });
+        Ok(())
+    }
+
+    /// 文を実行し、最後に実行した式文・代入文・return の値を返す
+    /// 値を持つ文が無ければ Value::Unit を返す
+    pub fn eval(&mut self, syntax: Syntax, env: &mut Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+        match syntax {
+            Syntax::Statement(st) => {
+                self.last = Value::Unit;
+                // トップレベルの return は従来どおり env に "return" として残す
+                let value = match self.exec(st, env, ft)? {
+                    Flow::Normal => return Ok(std::mem::replace(&mut self.last, Value::Unit)),
+                    Flow::Return(value) => value,
+                    Flow::TailCall { id, args } => self.call(id, args, ft)?,
+                };
+                env.insert("return".to_string(), value.clone());
+                Ok(value)
+            }
         }
-        Statement::FunctionDefine { id, params, body } => {
-            ft.insert(id, Declaration::Function { params, body });
+    }
+
+    // 1 ステップ分の燃料を消費する。中断要求があればここで打ち切る
+    fn tick(&mut self) -> Result<(), RuntimeError> {
+        if let Some(token) = &self.cancel {
+            if token.is_cancelled() {
+                return Err(RuntimeError::Interrupted);
+            }
         }
-        Statement::FunctionCall { expr } => {
-            calc(expr, env, ft);
+        match self.fuel {
+            Some(0) => Err(RuntimeError::OutOfFuel),
+            Some(n) => {
+                self.fuel = Some(n - 1);
+                Ok(())
+            }
+            None => Ok(()),
         }
-        Statement::Return { expr } => {
-            let value = calc(*expr, env, ft);
-            env.insert("return".to_string(), value);
+    }
+
+    // 文を実行する
+    fn exec(&mut self, statement: Statement, env: &mut Env, ft: &mut FunctionTable) -> Result<Flow, RuntimeError> {
+        self.tick()?;
+        match statement {
+            Statement::ExprStatement { expr } => {
+                let value = self.calc(expr, env, ft)?;
+                if let Some(output) = &mut self.output {
+                    output(&value);
+                }
+                self.last = value;
+            }
+            Statement::CompoundStatement { st1, st2 } => {
+                // return した後の文は実行しない
+                return match self.exec(*st1, env, ft)? {
+                    Flow::Normal => self.exec(*st2, env, ft),
+                    flow => Ok(flow),
+                };
+            }
+            Statement::FunctionDefine { id, params, body } => {
+                if self.builtin_policy == BuiltinPolicy::Protected && builtins::lookup(&id).is_some() {
+                    return Err(RuntimeError::BuiltinRedefinition(id));
+                }
+                ft.insert(id, Declaration::Function { params, body });
+            }
+            Statement::Return { expr } => {
+                return match *expr {
+                    // 末尾呼び出しは引数だけ評価して呼び出し元に任せる
+                    Expr::FunctionCall { id, args } => Ok(Flow::TailCall {
+                        args: self.calc_args(args, env, ft)?,
+                        id,
+                    }),
+                    expr => Ok(Flow::Return(self.calc(expr, env, ft)?)),
+                };
+            }
+            Statement::Assign { id, e } => {
+                let value = self.calc(*e, env, ft)?;
+                env.insert(id, value.clone());
+                self.last = value;
+            }
+            Statement::If {
+                condition,
+                then,
+                els,
+            } => {
+                return if self.calc(*condition, env, ft)?.is_positive() {
+                    self.exec(*then, env, ft)
+                } else {
+                    self.exec(*els, env, ft)
+                };
+            }
+            Statement::Null => {}
+        }
+        Ok(Flow::Normal)
+    }
+
+    // 式を計算する
+    fn calc(&mut self, expr: Expr, env: &mut Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+        self.tick()?;
+        match expr {
+            Expr::Binary { op, lhs, rhs } => {
+                let lhs = self.calc(*lhs, env, ft)?;
+                let rhs = self.calc(*rhs, env, ft)?;
+                self.binary(op, lhs, rhs)
+            }
+            Expr::Unary { op: UnOp::Neg, expr } => {
+                let value = self.calc(*expr, env, ft)?;
+                match value {
+                    // 整数の符号反転もオーバーフローの扱いに従う
+                    Value::Int(_) | Value::Big(_) => self.fit(-value.to_bigint()),
+                    value => Ok(-value),
+                }
+            }
+            Expr::Unary { op: UnOp::BitNot, expr } => match self.calc(*expr, env, ft)? {
+                value @ (Value::Int(_) | Value::Big(_)) => self.fit(!value.to_bigint()),
+                _ => Err(RuntimeError::Unsupported("整数以外のビット反転".to_string())),
+            },
+            Expr::Unary { op: UnOp::Factorial, expr } => match self.calc(*expr, env, ft)? {
+                Value::Int(n) if n >= 0 => self.factorial(n),
+                _ => Err(RuntimeError::Unsupported("負の数や整数以外の階乗".to_string())),
+            },
+            Expr::Unary { op: UnOp::Percent, expr } => match self.calc(*expr, env, ft)? {
+                // 整数と固定小数点数は小数点を 2 桁ずらした固定小数点数にする
+                value @ (Value::Int(_) | Value::Big(_)) => Ok(Value::Decimal { unscaled: value.to_bigint(), scale: 2 }),
+                Value::Decimal { unscaled, scale } => Ok(Value::Decimal { unscaled, scale: scale + 2 }),
+                value => self.binary(BinOp::Div, value, Value::Int(100)),
+            },
+            Expr::Number(Value::Big(n)) => self.fit(n),
+            Expr::Number(n) => Ok(n),
+            // 変数が無ければ組み込み定数 (pi, e) を探す
+            Expr::Var(s) => match env.get(&s).cloned().or_else(|| builtins::constant(&s)) {
+                Some(num) => Ok(num),
+                None => Err(RuntimeError::UndefinedVariable(s)),
+            },
+            Expr::FunctionCall { id, args } => {
+                let args = self.calc_args(args, env, ft)?;
+                self.call(id, args, ft)
+            },
+        }
+    }
+
+    // 二項演算を計算する
+    pub(crate) fn binary(&self, op: BinOp, lhs: Value, rhs: Value) -> Result<Value, RuntimeError> {
+        if lhs == Value::Unit || rhs == Value::Unit {
+            return Err(RuntimeError::Unsupported("値のない式の演算".to_string()));
         }
-        Statement::Assign { id, e } => {
-            let value = calc(*e, env, ft);
-            env.insert(id, value);
+        match op {
+            BinOp::Pow => return self.power(lhs, rhs),
+            BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr => return self.bitwise(op, lhs, rhs),
+            _ => {}
         }
-        Statement::If {
-            condition,
-            then,
-            els,
-        } => {
-            if calc(*condition, env, ft) > 0 {
-                exec(*then, env, ft)
-            } else {
-                exec(*els, env, ft)
+
+        // 複素数が含まれる場合は複素数で計算する
+        if matches!(lhs, Value::Complex { .. }) || matches!(rhs, Value::Complex { .. }) {
+            if op == BinOp::Rem {
+                return Err(RuntimeError::Unsupported("複素数の剰余".to_string()));
             }
+            return complex(op, lhs.to_complex(), rhs.to_complex());
+        }
+
+        // 浮動小数点数が含まれる場合は浮動小数点数で計算する
+        if matches!(lhs, Value::Float(_)) || matches!(rhs, Value::Float(_)) {
+            let (l, r) = (lhs.to_f64(), rhs.to_f64());
+            return match op {
+                BinOp::Add => Ok(Value::Float(l + r)),
+                BinOp::Sub => Ok(Value::Float(l - r)),
+                BinOp::Mul => Ok(Value::Float(l * r)),
+                BinOp::Div | BinOp::Rem if r == 0.0 => Err(RuntimeError::DivisionByZero),
+                BinOp::Div => Ok(Value::Float(l / r)),
+                BinOp::Rem => Ok(Value::Float(l % r)),
+                _ => unreachable!("{:?} は binary の先頭で処理する", op),
+            };
+        }
+
+        // 固定小数点数が含まれる場合は固定小数点数で計算する
+        if matches!(lhs, Value::Decimal { .. }) || matches!(rhs, Value::Decimal { .. }) {
+            let scale = lhs.scale().max(rhs.scale()).max(self.decimal_scale);
+            let result = exact(op, lhs.to_rational(), rhs.to_rational())?;
+            return Ok(Value::Rational(result).to_decimal(scale, self.rounding));
+        }
+
+        // 有理数が含まれるか、Exact での割り算は有理数で計算する
+        let exact_division = op == BinOp::Div && self.division == Division::Exact;
+        if exact_division || matches!(lhs, Value::Rational(_)) || matches!(rhs, Value::Rational(_)) {
+            return self.rational(exact(op, lhs.to_rational(), rhs.to_rational())?);
+        }
+
+        // i32 同士で収まる場合はそのまま計算する
+        if let (Value::Int(l), Value::Int(r)) = (&lhs, &rhs) {
+            let result = match op {
+                BinOp::Add => l.checked_add(*r),
+                BinOp::Sub => l.checked_sub(*r),
+                BinOp::Mul => l.checked_mul(*r),
+                BinOp::Div | BinOp::Rem if *r == 0 => return Err(RuntimeError::DivisionByZero),
+                BinOp::Div => l.checked_div(*r),
+                BinOp::Rem => l.checked_rem(*r),
+                _ => unreachable!("{:?} は binary の先頭で処理する", op),
+            };
+            if let Some(n) = result {
+                return Ok(Value::Int(n));
+            }
+        }
+
+        // 収まらない場合は多倍長整数で正確な値を求めてからモードに合わせる
+        let (l, r) = (lhs.to_bigint(), rhs.to_bigint());
+        match op {
+            BinOp::Add => self.fit(l + r),
+            BinOp::Sub => self.fit(l - r),
+            BinOp::Mul => self.fit(l * r),
+            BinOp::Div | BinOp::Rem if r.is_zero() => Err(RuntimeError::DivisionByZero),
+            BinOp::Rem => self.fit(l % r),
+            // i32::MIN / -1 は Promote 以外のモードではエラーにする
+            BinOp::Div => match (self.overflow, Value::from_bigint(l / r)) {
+                (Overflow::Promote, n) | (_, n @ Value::Int(_)) => Ok(n),
+                _ => Err(RuntimeError::Overflow),
+            },
+            _ => unreachable!("{:?} は binary の先頭で処理する", op),
         }
-        _ => panic!("実行できない Statement {:?} を実行しようとした", statement),
     }
-}
 
-// 式を計算する
-fn calc(expr: Expr, env: &mut Env, ft: &mut FunctionTable) -> i32 {
-    match expr {
-        Expr::Binary { op, lhs, rhs } => match op {
-            BinOp::Add => return calc(*lhs, env, ft) + calc(*rhs, env, ft),
-            BinOp::Sub => return calc(*lhs, env, ft) - calc(*rhs, env, ft),
-            BinOp::Mul => return calc(*lhs, env, ft) * calc(*rhs, env, ft),
-            BinOp::Div => return calc(*lhs, env, ft) / calc(*rhs, env, ft),
-        },
-        Expr::Number(n) => n,
-        Expr::Var(s) => match env.get(&s.to_string()) {
-            Some(num) => *num,
-            None => panic!("環境 env に変数名 {:?} が登録されていない", &s.to_string()),
-        },
-        Expr::FunctionCall { id, args } => {
-            let mut lenv = Env::new();
-            match ft.get(&id.to_string()) {
-                Some(Declaration::Function { params, body }) => {
-                    for (param, arg) in params.iter().zip(args.clone().iter()) {
-                        lenv.insert(param.to_string(), calc(arg.clone(), env, &mut ft.clone()));
-                    }
+    // ビット演算とシフトを計算する。整数 (2 の補数表現) だけを対象にする
+    fn bitwise(&self, op: BinOp, lhs: Value, rhs: Value) -> Result<Value, RuntimeError> {
+        let (l, r) = match (&lhs, &rhs) {
+            (Value::Int(_) | Value::Big(_), Value::Int(_) | Value::Big(_)) => (lhs.to_bigint(), rhs.to_bigint()),
+            _ => return Err(RuntimeError::Unsupported("整数以外のビット演算".to_string())),
+        };
+        match op {
+            BinOp::BitAnd => self.fit(l & r),
+            BinOp::BitOr => self.fit(l | r),
+            BinOp::BitXor => self.fit(l ^ r),
+            _ => {
+                let amount = match rhs {
+                    Value::Int(n @ 0..=31) => n as usize,
+                    _ => return Err(RuntimeError::ShiftOutOfRange),
+                };
+                match op {
+                    BinOp::Shl => self.fit(l << amount),
+                    _ => self.fit(l >> amount),
+                }
+            }
+        }
+    }
+
+    // べき乗を計算する。指数が i32 の整数なら正確に、それ以外は浮動小数点数や複素数で計算する
+    fn power(&self, base: Value, exp: Value) -> Result<Value, RuntimeError> {
+        match (&base, &exp) {
+            // 複素数の整数乗は exp と ln を通さずに掛け算で計算する
+            (Value::Complex { .. }, Value::Int(e)) => return complex_pow(base.to_complex(), *e),
+            (Value::Float(_) | Value::Complex { .. }, _) => {}
+            (_, Value::Int(e)) => {
+                let r = base.to_rational();
+                if r.is_zero() && *e < 0 {
+                    return Err(RuntimeError::DivisionByZero);
+                }
+                // 結果が大きすぎるものは計算する前に断る
+                let bits = r.numer().bits().max(r.denom().bits()).saturating_sub(1);
+                if bits.saturating_mul(e.unsigned_abs() as u64) > MAX_POWER_BITS {
+                    return match self.overflow {
+                        Overflow::Wrapping if r.is_integer() && *e > 0 => {
+                            self.fit(r.numer().modpow(&BigInt::from(*e), &(BigInt::from(1) << 32)))
+                        }
+                        Overflow::Saturating if r.is_integer() && *e > 0 => {
+                            self.fit(if r.is_negative() && e % 2 == 1 { BigInt::from(i64::MIN) } else { BigInt::from(i64::MAX) })
+                        }
+                        _ => Err(RuntimeError::Overflow),
+                    };
+                }
+                let result = r.pow(*e);
+                return match base {
+                    Value::Decimal { scale, .. } => Ok(Value::Rational(result).to_decimal(scale.max(self.decimal_scale), self.rounding)),
+                    _ => self.rational(result),
+                };
+            }
+            (_, Value::Big(_)) => return Err(RuntimeError::Overflow),
+            _ => {}
+        }
+
+        let ((a, b), (c, d)) = (base.to_complex(), exp.to_complex());
+        // 実数の範囲で計算できる場合
+        if b == 0.0 && d == 0.0 && (a >= 0.0 || c.fract() == 0.0) {
+            return Ok(Value::Float(a.powf(c)));
+        }
+        if a == 0.0 && b == 0.0 {
+            return Ok(Value::Float(0.0));
+        }
+        // z^w = exp(w * ln z)
+        let (ln_r, theta) = (a.hypot(b).ln(), b.atan2(a));
+        let (x, y) = (c * ln_r - d * theta, c * theta + d * ln_r);
+        Ok(Value::from_complex(x.exp() * y.cos(), x.exp() * y.sin()))
+    }
+
+    // 階乗。掛け算 1 回ごとに燃料を消費する
+    // Promote 以外では i32 に収まらなくなった時点で打ち切る
+    fn factorial(&mut self, n: i32) -> Result<Value, RuntimeError> {
+        let mut product = BigInt::from(1);
+        for k in 2..=n {
+            self.tick()?;
+            match self.overflow {
+                // 下位 32 ビットだけで計算すれば結果は同じ
+                Overflow::Wrapping => product = BigInt::from(product.to_i32().unwrap().wrapping_mul(k)),
+                _ => product *= k,
+            }
+            // 下位 32 ビットが 0 になればそれ以降も 0 のまま
+            if self.overflow == Overflow::Wrapping && product.is_zero() {
+                break;
+            }
+            if self.overflow != Overflow::Promote && product.to_i32().is_none() {
+                return self.fit(product);
+            }
+        }
+        self.fit(product)
+    }
+
+    // 有理数の計算結果を値にする
+    fn rational(&self, result: BigRational) -> Result<Value, RuntimeError> {
+        // 整数になった場合は整数と同じくオーバーフローの扱いに従う
+        if result.is_integer() {
+            return self.fit(result.to_integer());
+        }
+        Ok(Value::Rational(result))
+    }
 
-                    exec(*body.clone(), &mut lenv, &mut ft.clone());
-                    match lenv.get(&"return".to_string()) {
-                        Some(i) => *i,
-                        None => todo!("関数が値を返さない場合の挙動が未定義"),
+    // オーバーフローの扱いに応じて整数を値にする
+    pub(crate) fn fit(&self, n: BigInt) -> Result<Value, RuntimeError> {
+        if let Some(i) = n.to_i32() {
+            return Ok(Value::Int(i));
+        }
+        match self.overflow {
+            Overflow::Promote => Ok(Value::Big(n)),
+            Overflow::Error => Err(RuntimeError::Overflow),
+            Overflow::Wrapping => Ok(Value::Int((n & BigInt::from(u32::MAX)).to_u32().unwrap() as i32)),
+            Overflow::Saturating if n.is_negative() => Ok(Value::Int(i32::MIN)),
+            Overflow::Saturating => Ok(Value::Int(i32::MAX)),
+        }
+    }
+
+    // 関数呼び出しの引数を呼び出し元の環境で計算する
+    fn calc_args(&mut self, args: Vec<Expr>, env: &mut Env, ft: &mut FunctionTable) -> Result<Vec<Value>, RuntimeError> {
+        args.into_iter().map(|arg| self.calc(arg, env, &mut ft.clone())).collect()
+    }
+
+    // 関数を呼び出す
+    // 本体が末尾呼び出しで終わった場合は Rust のスタックを積まずにループで次の関数へ移る
+    fn call(&mut self, mut id: String, mut args: Vec<Value>, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+        loop {
+            // 組み込み関数は Overridable で同じ名前の関数が定義されている場合を除いて優先する
+            if let Some((arity, f)) = builtins::lookup(&id) {
+                if self.builtin_policy == BuiltinPolicy::Protected || !ft.contains_key(&id) {
+                    return builtins::call(self, &id, arity, f, &args);
+                }
+            }
+
+            let (params, body) = match ft.get(&id) {
+                Some(Declaration::Function { params, body }) => (params.clone(), body.clone()),
+                Some(Declaration::Native { arity, func }) => {
+                    if args.len() != *arity {
+                        return Err(RuntimeError::Arity { id, expected: *arity, found: args.len() });
                     }
+                    return (func.0)(&args);
+                }
+                None => return Err(RuntimeError::UndefinedFunction(id)),
+            };
+
+            let mut lenv = Env::new();
+            for (param, arg) in params.into_iter().zip(args) {
+                lenv.insert(param, arg);
+            }
+
+            // 関数の本体で実行した文の値は eval の返す値にしない
+            let last = std::mem::replace(&mut self.last, Value::Unit);
+            let flow = self.exec(*body, &mut lenv, &mut ft.clone());
+            self.last = last;
+            match flow? {
+                Flow::Return(value) => return Ok(value),
+                Flow::TailCall { id: next_id, args: next_args } => {
+                    id = next_id;
+                    args = next_args;
                 }
-                None => panic!("関数テーブル ft に関数名 {:?} が登録されていない", id),
+                Flow::Normal => return Err(RuntimeError::MissingReturn(id)),
             }
-        },
+        }
     }
 }
+
+// 有理数で正確に二項演算を計算する
+fn exact(op: BinOp, l: BigRational, r: BigRational) -> Result<BigRational, RuntimeError> {
+    match op {
+        BinOp::Add => Ok(l + r),
+        BinOp::Sub => Ok(l - r),
+        BinOp::Mul => Ok(l * r),
+        BinOp::Div | BinOp::Rem if r.is_zero() => Err(RuntimeError::DivisionByZero),
+        BinOp::Div => Ok(l / r),
+        BinOp::Rem => Ok(&l - &r * (&l / &r).trunc()),
+        _ => unreachable!("{:?} は binary の先頭で処理する", op),
+    }
+}
+
+/// 組み込み先のプログラムの関数を既定の設定の Evaluator で関数テーブルに登録する
+pub fn register_native<F>(ft: &mut FunctionTable, id: &str, arity: usize, func: F) -> Result<(), RuntimeError>
+where
+    F: Fn(&[Value]) -> Result<Value, RuntimeError> + Send + Sync + 'static,
+{
+    Evaluator::new().register_native(ft, id, arity, func)
+}
+
+// 複素数の二項演算を計算する
+fn complex(op: BinOp, (a, b): (f64, f64), (c, d): (f64, f64)) -> Result<Value, RuntimeError> {
+    match op {
+        BinOp::Add => Ok(Value::from_complex(a + c, b + d)),
+        BinOp::Sub => Ok(Value::from_complex(a - c, b - d)),
+        BinOp::Mul => Ok(Value::from_complex(a * c - b * d, a * d + b * c)),
+        BinOp::Div if c == 0.0 && d == 0.0 => Err(RuntimeError::DivisionByZero),
+        BinOp::Div => {
+            let den = c * c + d * d;
+            Ok(Value::from_complex((a * c + b * d) / den, (b * c - a * d) / den))
+        }
+        _ => unreachable!("{:?} は binary の先頭で処理する", op),
+    }
+}
+
+// 複素数の整数乗。二乗と掛け算を繰り返す
+fn complex_pow(z: (f64, f64), e: i32) -> Result<Value, RuntimeError> {
+    let (mut result, mut square, mut n) = ((1.0, 0.0), z, e.unsigned_abs());
+    while n > 0 {
+        if n & 1 == 1 {
+            result = complex(BinOp::Mul, result, square)?.to_complex();
+        }
+        square = complex(BinOp::Mul, square, square)?.to_complex();
+        n >>= 1;
+    }
+    if e < 0 {
+        return complex(BinOp::Div, (1.0, 0.0), result);
+    }
+    Ok(Value::from_complex(result.0, result.1))
+}
+
+pub fn eval(syntax: Syntax, env: &mut Env, ft: &mut FunctionTable) -> Result<Value, RuntimeError> {
+    Evaluator::new().eval(syntax, env, ft)
+}
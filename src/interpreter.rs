@@ -0,0 +1,89 @@
+use crate::evaluator::Evaluator;
+use crate::parser;
+use crate::scanner;
+use crate::Declaration;
+use crate::Env;
+use crate::Error;
+use crate::FunctionTable;
+use crate::RuntimeError;
+use crate::Syntax;
+use crate::Value;
+
+/// 環境 env と関数テーブル ft を持ち、文字列をそのまま評価する
+/// 組み込み先のプログラムはこれを通して計算機を使う
+pub struct Interpreter {
+    env: Env,
+    ft: FunctionTable,
+    evaluator: Evaluator,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter {
+            env: Env::new(),
+            ft: FunctionTable::new(),
+            evaluator: Evaluator::new(),
+        }
+    }
+
+    /// 文字列を字句解析・構文解析して評価し、最後に実行した式文・代入文・return の値を返す
+    /// 値を持つ文が無ければ Value::Unit を返す
+    pub fn eval_str(&mut self, src: &str) -> Result<Value, Error> {
+        let syntax = parser::parser(scanner::scanner(src)?)?;
+        Ok(self.eval(syntax)?)
+    }
+
+    /// 構文解析済みの文を評価する
+    pub fn eval(&mut self, syntax: Syntax) -> Result<Value, RuntimeError> {
+        self.evaluator.eval(syntax, &mut self.env, &mut self.ft)
+    }
+
+    /// 変数の値を取り出す
+    pub fn get_var(&self, name: &str) -> Option<&Value> {
+        self.env.get(name)
+    }
+
+    /// 変数に値を代入する
+    pub fn set_var(&mut self, name: &str, value: impl Into<Value>) {
+        self.env.insert(name.to_string(), value.into());
+    }
+
+    /// 組み込み先のプログラムの関数を登録する
+    /// 組み込み関数と同じ名前の扱いは Evaluator の BuiltinPolicy に従う
+    pub fn define_function<F>(&mut self, name: &str, arity: usize, func: F) -> Result<(), RuntimeError>
+    where
+        F: Fn(&[Value]) -> Result<Value, RuntimeError> + Send + Sync + 'static,
+    {
+        self.evaluator.register_native(&mut self.ft, name, arity, func)
+    }
+
+    /// 変数と定義した関数をすべて消す
+    /// define_function で登録した関数と Evaluator の設定はそのまま残す
+    pub fn reset(&mut self) {
+        self.env.clear();
+        self.ft.retain(|_, declaration| matches!(declaration, Declaration::Native { .. }));
+    }
+
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+
+    pub fn functions(&self) -> &FunctionTable {
+        &self.ft
+    }
+
+    pub fn evaluator(&self) -> &Evaluator {
+        &self.evaluator
+    }
+
+    /// 演算の設定や出力先を変えるために Evaluator を取り出す
+    pub fn evaluator_mut(&mut self) -> &mut Evaluator {
+        &mut self.evaluator
+    }
+}
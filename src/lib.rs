@@ -0,0 +1,740 @@
+// 既存コードの書き方（明示的な return や改行付き print! など）に合わせるため許可する
+#![allow(
+    clippy::needless_return,
+    clippy::print_with_newline,
+    clippy::unused_unit,
+    clippy::upper_case_acronyms,
+    clippy::enum_variant_names,
+    clippy::redundant_field_names
+)]
+// ParseError は位置を示すためにトークンをそのまま持つので大きくなる
+#![allow(clippy::result_large_err)]
+
+mod builtins;
+pub mod evaluator;
+mod interpreter;
+pub mod parser;
+pub mod scanner;
+mod value;
+
+pub mod enums;
+pub use crate::enums::BinOp;
+pub use crate::enums::BuiltinPolicy;
+pub use crate::enums::Env;
+pub use crate::enums::Error;
+pub use crate::enums::FunctionTable;
+pub use crate::enums::NativeFn;
+pub use crate::enums::NativeFnBody;
+pub use crate::enums::Expr;
+pub use crate::enums::Declaration;
+pub use crate::enums::Division;
+pub use crate::enums::Overflow;
+pub use crate::enums::ParseError;
+pub use crate::enums::Rounding;
+pub use crate::enums::RuntimeError;
+pub use crate::enums::Statement;
+pub use crate::enums::Syntax;
+pub use crate::enums::Token;
+pub use crate::enums::UnOp;
+pub use crate::enums::Value;
+pub use crate::evaluator::CancelToken;
+pub use crate::evaluator::Evaluator;
+pub use crate::interpreter::Interpreter;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_assign() {
+        let str = "x = 123";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 実行後に x = 123 が代入されていること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 123);
+    }
+
+    #[test]
+    fn test_negative() {
+        let str = "x = -1";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 実行後に x = -1 が代入されていること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], -1);
+    }
+
+    #[test]
+    fn test_if() {
+        let str = "if 0 { x = 2 } else { x = 3 }";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 実行後に x = 3 が代入されていること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 3);
+    }
+
+    #[test]
+    fn test_addition() {
+        let str = "x = 1 + 2 + 3";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 実行後に x = 6 が代入されていること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 6);
+    }
+
+    #[test]
+    fn test_subtraction() {
+        let str = "x = 1 - 2 - 3";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 実行後に x = -4 が代入されていること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], -4);
+    }
+
+    #[test]
+    fn test_multiplication() {
+        let str = "x = 1 * 2 * 3";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 実行後に x = 6 が代入されていること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 6);
+    }
+
+    #[test]
+    fn test_division() {
+        let str = "x = 4 / 2 / 2";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 実行後に x = 1 が代入されていること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 1);
+    }
+
+    #[test]
+    fn test_parenthesis() {
+        let str = "x = 2 * (3 + 4) ";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 実行後に x = 14 が代入されていること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 14);
+    }
+
+    #[test]
+    fn test_compound_statement() {
+        let str = "if 0 { x = 0 } else { x = 1 } ; if x { x = 3 } else { x = 4 }";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 実行後に x = 3 が代入されていること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 3);
+    }
+
+    #[test]
+    fn test_function() {
+        let str = "fn add(a, b) { return a + b } ; x = add(1, 2)";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 実行後に x = 3 が代入されていること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 3);
+    }
+
+    #[test]
+    fn test_tail_call() {
+        let str = "fn count(n, acc) { if n { return count(n - 1, acc + 1) } else { return acc } } ; x = count(100000, 0)";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 末尾再帰が深くてもスタックを使い切らず x = 100000 が代入されていること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 100000);
+    }
+
+    #[test]
+    fn test_fuel() {
+        let str = "x = 1 + 2";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+        let mut evaluator = evaluator::Evaluator::with_fuel(10);
+
+        // Assign, Binary, Number, Number の 4 ステップを消費していること
+        evaluator.eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 3);
+        assert_eq!(evaluator.fuel(), Some(6));
+    }
+
+    #[test]
+    fn test_out_of_fuel() {
+        let str = "fn forever(n) { return forever(n) } ; forever(1)";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+        let mut evaluator = evaluator::Evaluator::with_fuel(1000);
+
+        // 無限ループが OutOfFuel で打ち切られること
+        let result = evaluator.eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::OutOfFuel));
+        assert_eq!(evaluator.fuel(), Some(0));
+    }
+
+    #[test]
+    fn test_cancel() {
+        let str = "fn forever(n) { return forever(n) } ; forever(1)";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+        let token = CancelToken::new();
+        let mut evaluator = Evaluator::new();
+        evaluator.set_cancel_token(token.clone());
+
+        // 別スレッドから中断された無限ループが Interrupted で打ち切られること
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            token.cancel();
+        });
+        let result = evaluator.eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft);
+        canceller.join().unwrap();
+        assert_eq!(result, Err(RuntimeError::Interrupted));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let str = "x = 2147483647 + 1";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // Error ではオーバーフローがエラーになること
+        let mut evaluator = Evaluator::new();
+        evaluator.set_overflow(Overflow::Error);
+        let result = evaluator.eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::Overflow));
+
+        // Wrapping では折り返し、Saturating では最大値に張り付くこと
+        evaluator.set_overflow(Overflow::Wrapping);
+        evaluator.eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], i32::MIN);
+
+        evaluator.set_overflow(Overflow::Saturating);
+        evaluator.eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], i32::MAX);
+    }
+
+    #[test]
+    fn test_division_error() {
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+        let mut evaluator = Evaluator::new();
+        evaluator.set_overflow(Overflow::Wrapping);
+
+        // 0 除算と i32::MIN / -1 はモードに関わらずエラーになること
+        let result = evaluator.eval(parser::parser(scanner::scanner("x = 1 / 0").unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::DivisionByZero));
+        let result = evaluator.eval(parser::parser(scanner::scanner("x = (-2147483647 - 1) / -1").unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::Overflow));
+    }
+
+    #[test]
+    fn test_bigint() {
+        let str = "fn fact(n) { if n { return n * fact(n - 1) } else { return 1 } } ; x = fact(30) ; y = x / 10000000000000000000000000 ; z = 99999999999 - 99999999998";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 既定では i32 を超えると多倍長整数になり、収まれば i32 に戻ること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"].to_string(), "265252859812191058636308480000000");
+        assert_eq!(env["y"], 26525285);
+        assert_eq!(env["z"], 1);
+    }
+
+    #[test]
+    fn test_rational() {
+        let str = "x = 1/3 + 1/6 ; y = 4/2 ; z = 1/3";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 既定では切り捨てで 0 になること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 0);
+
+        // Exact では約分された有理数になり、割り切れれば整数になること
+        let mut evaluator = Evaluator::new();
+        evaluator.set_division(Division::Exact);
+        evaluator.eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"].to_string(), "1/2");
+        assert_eq!(env["y"], 2);
+        assert_eq!(env["z"].to_decimal_string(3), "0.333");
+        assert_eq!((-env["x"].clone()).to_decimal_string(2), "-0.50");
+    }
+
+    #[test]
+    fn test_decimal() {
+        let str = "x = 12.50d + 0.5d ; y = 10.00d / 3 ; z = 0.125d * 1 ; w = -1.005d * 1";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 既定では小数点以下 2 桁で銀行丸めになり、末尾の 0 も表示されること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"].to_string(), "13.00");
+        assert_eq!(env["y"].to_string(), "3.33");
+        assert_eq!(env["z"].to_string(), "0.125");
+        assert_eq!(env["w"].to_string(), "-1.005");
+
+        // 桁数と丸め方を変えられること
+        let mut evaluator = Evaluator::new();
+        evaluator.set_decimal_scale(4);
+        evaluator.set_rounding(Rounding::Truncate);
+        evaluator.eval(parser::parser(scanner::scanner("y = 2d / 3").unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["y"].to_string(), "0.6666");
+
+        evaluator.set_decimal_scale(0);
+        evaluator.set_rounding(Rounding::HalfEven);
+        evaluator.eval(parser::parser(scanner::scanner("x = 2.5d * 1d").unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"].to_string(), "2.5");
+        evaluator.eval(parser::parser(scanner::scanner("x = 5d / 2 ; y = 7d / 2").unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"].to_string(), "2");
+        assert_eq!(env["y"].to_string(), "4");
+
+        evaluator.set_rounding(Rounding::HalfUp);
+        evaluator.eval(parser::parser(scanner::scanner("x = 5d / 2").unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"].to_string(), "3");
+    }
+
+    #[test]
+    fn test_complex() {
+        let str = "x = sqrt(-1) ; y = (1+2i)*(3-4i) ; z = abs(3+4i) ; w = 1i * 1i ; v = 1.5i / 2";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 虚数リテラルと複素数の演算ができること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"].to_string(), "1i");
+        assert_eq!(env["y"].to_string(), "11+2i");
+        assert_eq!(env["z"].to_string(), "5");
+        assert_eq!(env["w"].to_string(), "-1");
+        assert_eq!(env["v"].to_string(), "0.75i");
+        // 虚部が 0 のリテラルは実数になること
+        let str = "n = 0i";
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["n"], Value::Float(0.0));
+
+        // 複素数の整数乗が誤差なく計算されること
+        let str = "p = 1i ^ 2 ; q = 1i ^ 3 ; r = (1+1i) ^ 4 ; s = 2i ^ -2 ; t = 1i ^ 0";
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["p"].to_string(), "-1");
+        assert_eq!(env["q"].to_string(), "-1i");
+        assert_eq!(env["r"].to_string(), "-4");
+        assert_eq!(env["s"].to_string(), "-0.25");
+        assert_eq!(env["t"].to_string(), "1");
+
+        // re, im, conj, arg が使えること
+        let str = "a = re(y) ; b = im(y) ; c = conj(y) ; d = arg(-1)";
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["a"].to_string(), "11");
+        assert_eq!(env["b"].to_string(), "2");
+        assert_eq!(env["c"].to_string(), "11-2i");
+        assert_eq!(env["d"], Value::Float(std::f64::consts::PI));
+    }
+
+    #[test]
+    fn test_remainder() {
+        let str = "x = 7 % 3 ; y = -7 % 3 ; z = 1 + 7 % 3 * 2 ; w = 7.5d % 2";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 剰余の符号は割られる数に合わせ、* と同じ優先順位になること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 1);
+        assert_eq!(env["y"], -1);
+        assert_eq!(env["z"], 3);
+        assert_eq!(env["w"].to_string(), "1.50");
+    }
+
+    #[test]
+    fn test_power() {
+        let str = "x = 2 ^ 3 ^ 2 ; y = -2 ^ 2 ; z = 2 ^ -1 ; w = 2 * 3 ^ 2 ; v = 2 ^ 100 ; u = 4 ^ (1 / 2.0d)";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // べき乗は右結合で単項マイナスより強く結合すること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 512);
+        assert_eq!(env["y"], -4);
+        assert_eq!(env["z"].to_string(), "1/2");
+        assert_eq!(env["w"], 18);
+        assert_eq!(env["v"].to_string(), "1267650600228229401496703205376");
+        assert_eq!(env["u"].to_string(), "2");
+    }
+
+    #[test]
+    fn test_power_limit() {
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 結果が大きすぎる累乗は計算せずに Overflow になること
+        let mut evaluator = Evaluator::new();
+        let result = evaluator.eval(parser::parser(scanner::scanner("x = 2 ^ 2147483647").unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::Overflow));
+
+        // 0、1、-1 の累乗はそのまま計算されること
+        evaluator.eval(parser::parser(scanner::scanner("a = 1 ^ 2147483647 ; b = -1 ^ 2147483647 ; c = 0 ^ 2147483647").unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["a"], 1);
+        assert_eq!(env["b"], -1);
+        assert_eq!(env["c"], 0);
+
+        // Wrapping では下位 32 ビット、Saturating では最大値になること
+        evaluator.set_overflow(Overflow::Wrapping);
+        evaluator.eval(parser::parser(scanner::scanner("x = 3 ^ 2147483647 ; y = 2 ^ 2147483647").unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 3u32.wrapping_pow(2147483647) as i32);
+        assert_eq!(env["y"], 0);
+
+        evaluator.set_overflow(Overflow::Saturating);
+        evaluator.eval(parser::parser(scanner::scanner("x = 3 ^ 2147483647 ; y = (-3) ^ 2147483647").unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], i32::MAX);
+        assert_eq!(env["y"], i32::MIN);
+    }
+
+    #[test]
+    fn test_bitwise() {
+        let str = "a = 12 & 10 ; b = 12 | 10 ; c = 12 xor 10 ; d = ~5 ; e = 1 << 4 + 1 ; f = -16 >> 2 ; g = 1 | 2 xor 3 & 4 << 1";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // C と同じ優先順位 (シフト > & > xor > |) で計算されること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["a"], 8);
+        assert_eq!(env["b"], 14);
+        assert_eq!(env["c"], 6);
+        assert_eq!(env["d"], -6);
+        assert_eq!(env["e"], 32);
+        assert_eq!(env["f"], -4);
+        assert_eq!(env["g"], 3);
+
+        // シフト量が範囲外ならエラーになること
+        let result = evaluator::eval(parser::parser(scanner::scanner("x = 1 << 32").unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::ShiftOutOfRange));
+        let result = evaluator::eval(parser::parser(scanner::scanner("x = 1 >> -1").unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::ShiftOutOfRange));
+    }
+
+    #[test]
+    fn test_implicit_multiplication() {
+        let str = "x = 3 ; a = 2 ; b = 1 ; fn f(n) { return n + 1 } ; p = 2x ; q = 3(4+5) ; r = (a+b)(a-b) ; s = 2f(3) ; t = 2x^2 ; u = 6 / 2x";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 隣り合う項が掛け算になり、f(x) は関数呼び出しのままであること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["p"], 6);
+        assert_eq!(env["q"], 27);
+        assert_eq!(env["r"], 3);
+        assert_eq!(env["s"], 8);
+        assert_eq!(env["t"], 18);
+        assert_eq!(env["u"], 9);
+    }
+
+    #[test]
+    fn test_number_suffix() {
+        let str = "delta = 3 ; dx = 2 ; d = 3 ; d2 = 4 ; i2 = 5 ; p = 2delta ; q = 3dx ; r = 2d ; s = 2d2 ; t = 2i2 ; u = 2i";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // d や i で始まる識別子が続く場合は掛け算になること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["p"], 6);
+        assert_eq!(env["q"], 6);
+        assert_eq!(env["s"], 8);
+        assert_eq!(env["t"], 10);
+        // d や i の 1 文字だけが続く場合は変数があってもリテラルの接尾辞になること
+        assert!(matches!(env["r"], Value::Decimal { scale: 0, .. }));
+        assert_eq!(env["u"].to_string(), "2i");
+    }
+
+    #[test]
+    fn test_postfix() {
+        let str = "a = 5! ; b = 50% ; c = 200 * 15% ; d = 20! ; e = 2^3! ; f = 10 % 3 ; g = 50% - 10 ; h = 12.5d% ; i = 7 % (-2) ; j = 200 * 15% + 1 ; k = 50% * 2 ; l = 100 + 10% - 5";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 後置の ! と % が計算され、項が続く % は剰余のままであること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["a"], 120);
+        assert_eq!(env["b"].to_string(), "0.50");
+        assert_eq!(env["c"].to_string(), "30.00");
+        assert_eq!(env["d"].to_string(), "2432902008176640000");
+        assert_eq!(env["e"], 64);
+        assert_eq!(env["f"], 1);
+        assert_eq!(env["g"].to_string(), "-9.50");
+        assert_eq!(env["h"].to_string(), "0.125");
+        // 括弧で囲んだ負の数との剰余が計算されること
+        assert_eq!(env["i"], 1);
+        // % の後に符号が続く場合はパーセントの後の足し算・引き算になること
+        assert_eq!(env["j"].to_string(), "31.00");
+        assert_eq!(env["k"].to_string(), "1.00");
+        assert_eq!(env["l"].to_string(), "95.10");
+    }
+
+    #[test]
+    fn test_factorial_limit() {
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 大きな階乗が掛け算ごとに燃料を消費して OutOfFuel で打ち切られること
+        let mut evaluator = Evaluator::with_fuel(1000);
+        let result = evaluator.eval(parser::parser(scanner::scanner("x = 100000000!").unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::OutOfFuel));
+
+        // Promote 以外では i32 を超えた時点で打ち切られること
+        let mut evaluator = Evaluator::new();
+        evaluator.set_overflow(Overflow::Error);
+        let result = evaluator.eval(parser::parser(scanner::scanner("x = 100000000!").unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::Overflow));
+
+        evaluator.set_overflow(Overflow::Saturating);
+        evaluator.eval(parser::parser(scanner::scanner("x = 100000000!").unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], i32::MAX);
+
+        // Wrapping では下位 32 ビットが残ること
+        evaluator.set_overflow(Overflow::Wrapping);
+        evaluator.eval(parser::parser(scanner::scanner("x = 13! ; y = 34!").unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 6227020800u64 as u32 as i32);
+        assert_eq!(env["y"], 0);
+    }
+
+    #[test]
+    fn test_function_shorthand() {
+        let str = "sq(x) = x * x ; hyp(a, b) = sqrt(sq(a) + sq(b)) ; x = sq(3) ; y = hyp(3, 4) ; z = sq(2) + 1 ; f() = 1 ; w = f()";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // name(params) = expr が関数定義になり、通常の呼び出しはそのまま式になること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 9);
+        assert_eq!(env["y"].to_string(), "5");
+        assert_eq!(env["z"], 5);
+        // 引数の無い関数も定義して呼び出せること
+        assert_eq!(env["w"], 1);
+        assert_eq!(
+            ft["sq"],
+            Declaration::Function {
+                params: vec!["x".to_string()],
+                body: Box::new(Statement::Return {
+                    expr: Box::new(Expr::Binary {
+                        op: BinOp::Mul,
+                        lhs: Box::new(Expr::Var("x".to_string())),
+                        rhs: Box::new(Expr::Var("x".to_string())),
+                    }),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_builtins() {
+        let str = "a = abs(-3) ; b = min(2, 1/1) ; c = max(1.5d, 2) ; d = pow(2, 10) ; f = floor(7/2.0d) ; g = ceil(-3.5d) ; h = round(2.5d) ; i = gcd(12, 18) ; j = lcm(4, 6)";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["a"], 3);
+        assert_eq!(env["b"], 1);
+        assert_eq!(env["c"], 2);
+        assert_eq!(env["d"], 1024);
+        assert_eq!(env["f"], 3);
+        assert_eq!(env["g"], -3);
+        assert_eq!(env["h"], 3);
+        assert_eq!(env["i"], 6);
+        assert_eq!(env["j"], 12);
+
+        // 三角関数、対数と定数 pi, e が使えること
+        let str = "s = sin(pi / 2) ; l = ln(e) ; t = log(1000) ; u = round(cos(pi))";
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["s"], Value::Float(1.0));
+        assert_eq!(env["l"], Value::Float(1.0));
+        assert_eq!(env["t"], Value::Float(3.0));
+        assert_eq!(env["u"], -1);
+
+        // 定義域外や引数の数の誤りはエラーになること
+        let result = evaluator::eval(parser::parser(scanner::scanner("x = ln(0)").unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::Domain("ln".to_string())));
+        let result = evaluator::eval(parser::parser(scanner::scanner("x = min(1)").unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::Arity { id: "min".to_string(), expected: 2, found: 1 }));
+
+        // abs も整数のオーバーフローの扱いに従うこと
+        let mut evaluator = Evaluator::new();
+        evaluator.set_overflow(Overflow::Error);
+        let result = evaluator.eval(parser::parser(scanner::scanner("x = abs(-2147483647 - 1)").unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::Overflow));
+    }
+
+    #[test]
+    fn test_builtin_policy() {
+        let str = "abs(x) = 42 ; y = abs(-1)";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // 既定では組み込み関数と同じ名前の関数は定義できないこと
+        let result = evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::BuiltinRedefinition("abs".to_string())));
+
+        // Overridable では定義した関数が優先されること
+        let mut evaluator = Evaluator::new();
+        evaluator.set_builtin_policy(BuiltinPolicy::Overridable);
+        evaluator.eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["y"], 42);
+    }
+
+    #[test]
+    fn test_native_function() {
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+        let rates: HashMap<i32, Value> = [(1, Value::Int(150)), (2, Value::Int(160))].into_iter().collect();
+        evaluator::register_native(&mut ft, "rate", 1, move |args| match &args[0] {
+            Value::Int(n) => rates.get(n).cloned().ok_or(RuntimeError::Native(format!("レート {} が無い", n))),
+            _ => Err(RuntimeError::Native("通貨番号は整数".to_string())),
+        })
+        .unwrap();
+
+        // 定義した関数と同じように呼び出せること (末尾呼び出しを含む)
+        let str = "x = 100 * rate(1) ; fn conv(n, c) { return rate(c) } ; y = conv(1, 2)";
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 15000);
+        assert_eq!(env["y"], 160);
+
+        // 引数の数の誤りと関数自身のエラーが RuntimeError として返ること
+        let result = evaluator::eval(parser::parser(scanner::scanner("x = rate(1, 2)").unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::Arity { id: "rate".to_string(), expected: 1, found: 2 }));
+        let result = evaluator::eval(parser::parser(scanner::scanner("x = rate(3)").unwrap()).unwrap(), &mut env, &mut ft);
+        assert_eq!(result, Err(RuntimeError::Native("レート 3 が無い".to_string())));
+
+        // Protected では組み込み関数と同じ名前を登録できず、Overridable では上書きできること
+        let result = evaluator::register_native(&mut ft, "abs", 1, |_| Ok(Value::Int(42)));
+        assert_eq!(result, Err(RuntimeError::BuiltinRedefinition("abs".to_string())));
+        let mut evaluator = Evaluator::new();
+        evaluator.set_builtin_policy(BuiltinPolicy::Overridable);
+        evaluator.register_native(&mut ft, "abs", 1, |_| Ok(Value::Int(42))).unwrap();
+        evaluator.eval(parser::parser(scanner::scanner("x = abs(-1)").unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 42);
+    }
+
+    #[test]
+    fn test_interpreter() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_var("rate", 3);
+        interpreter.define_function("double", 1, |args| match args[0] {
+            Value::Int(n) => Ok(Value::Int(n * 2)),
+            _ => Err(RuntimeError::Native("整数ではない".to_string())),
+        })
+        .unwrap();
+
+        // 最後に実行した文の値が返り、変数が残っていること
+        assert_eq!(interpreter.eval_str("x = double(rate) ; x + 1"), Ok(Value::Int(7)));
+        assert_eq!(interpreter.get_var("x"), Some(&Value::Int(6)));
+        // 値を持たない文は Unit を返すこと
+        assert_eq!(interpreter.eval_str("sq(n) = n * n"), Ok(Value::Unit));
+        assert_eq!(interpreter.eval_str("sq(x)"), Ok(Value::Int(36)));
+        // 関数の本体で実行した文の値は返らないこと
+        let str = "fn f(n) { y = 7 ; return n } ; if f(1) { g(x) = x } else { g(x) = x }";
+        assert_eq!(interpreter.eval_str(str), Ok(Value::Unit));
+        assert_eq!(interpreter.eval_str("z = 3 ; f(5) ; if f(1) { g(x) = x } else { g(x) = x }"), Ok(Value::Int(5)));
+
+        // 引数の無い関数を name() で呼び出せること
+        interpreter.define_function("answer", 0, |_| Ok(Value::Int(42))).unwrap();
+        assert_eq!(interpreter.eval_str("answer() + 1"), Ok(Value::Int(43)));
+
+        // 組み込み関数と同じ名前は登録できないこと
+        let result = interpreter.define_function("sqrt", 1, |args| Ok(args[0].clone()));
+        assert_eq!(result, Err(RuntimeError::BuiltinRedefinition("sqrt".to_string())));
+
+        // 別スレッドに渡して評価できること
+        let handle = std::thread::spawn(move || interpreter.eval_str("double(x)"));
+        assert_eq!(handle.join().unwrap(), Ok(Value::Int(12)));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut interpreter = Interpreter::new();
+
+        // 字句解析・構文解析・実行時のエラーが panic せずに返ること
+        assert_eq!(interpreter.eval_str("x = 1 $ 2"), Err(Error::Parse(ParseError::InvalidCharacter('$'))));
+        assert_eq!(interpreter.eval_str("x = 1.5"), Err(Error::Parse(ParseError::InvalidNumber("1.5".to_string()))));
+        assert_eq!(
+            interpreter.eval_str("x = (1 + 2"),
+            Err(Error::Parse(ParseError::Expected { expected: Token::RPAR, found: None }))
+        );
+        assert_eq!(interpreter.eval_str("1 + 2 )"), Err(Error::Parse(ParseError::Unexpected(Some(Token::RPAR)))));
+        assert_eq!(interpreter.eval_str("y"), Err(Error::Runtime(RuntimeError::UndefinedVariable("y".to_string()))));
+        assert_eq!(interpreter.eval_str("f(1)"), Err(Error::Runtime(RuntimeError::UndefinedFunction("f".to_string()))));
+        assert_eq!(
+            interpreter.eval_str("fn f(n) { x = n } ; f(1)"),
+            Err(Error::Runtime(RuntimeError::MissingReturn("f".to_string())))
+        );
+        // 空白文字として改行とタブを読み飛ばすこと
+        assert_eq!(interpreter.eval_str("\tx = 1 ;\n x + 1\r\n"), Ok(Value::Int(2)));
+    }
+
+    #[test]
+    fn test_interpreter_reset() {
+        let mut interpreter = Interpreter::new();
+        interpreter.define_function("first", 1, |args| Ok(args[0].clone())).unwrap();
+        interpreter.eval_str("x = 1 ; sq(n) = n * n").unwrap();
+
+        // 変数と定義した関数が消え、以前の名前は未定義になること
+        interpreter.reset();
+        assert!(interpreter.env().is_empty());
+        assert_eq!(interpreter.functions().len(), 1);
+        assert_eq!(interpreter.eval_str("sq(2)"), Err(Error::Runtime(RuntimeError::UndefinedFunction("sq".to_string()))));
+        // 組み込み先の関数は残ること
+        assert_eq!(interpreter.eval_str("first(1)"), Ok(Value::Int(1)));
+    }
+
+    #[test]
+    fn test_incomplete_input() {
+        // 入力の途中で終わっている場合だけ続きを待つエラーになること
+        let incomplete = |str: &str| matches!(parser::parser(scanner::scanner(str).unwrap()), Err(e) if e.is_incomplete());
+        assert!(incomplete("fn f(n) {"));
+        assert!(incomplete("x = (1 + 2"));
+        assert!(incomplete("x = 1 +"));
+        assert!(incomplete("if x"));
+        assert!(incomplete("if x { y = 1 }"));
+        assert!(!incomplete("x = 1 + 2)"));
+        assert!(!incomplete("fn f(n) {\n  return n\n}"));
+    }
+
+    #[test]
+    fn test_spans() {
+        let spans = scanner::spans("x = 1.5d $ f(2)");
+
+        // トークンごとの位置が返り、読めない文字の後も続けて読むこと
+        let ranges: Vec<_> = spans.iter().map(|(_, range)| range.clone()).collect();
+        assert_eq!(ranges, vec![0..1, 2..3, 4..8, 9..10, 11..12, 12..13, 13..14, 14..15]);
+        assert_eq!(spans[2].0, Ok(Token::NUMBER(Value::Decimal { unscaled: 15.into(), scale: 1 })));
+        assert_eq!(spans[3].0, Err(ParseError::InvalidCharacter('$')));
+        assert_eq!(spans[4].0, Ok(Token::IDENT("f".to_string())));
+    }
+
+    #[test]
+    fn test_identifier() {
+        let str = "_ = 1 ; _2 = 2 ; rate_2 = 3 ; x = _ + _2 + rate_2 + 2rate_2";
+        let mut env = Env::new();
+        let mut ft = FunctionTable::new();
+
+        // _ で始まる識別子と 2 文字目以降の数字が使えること
+        evaluator::eval(parser::parser(scanner::scanner(str).unwrap()).unwrap(), &mut env, &mut ft).unwrap();
+        assert_eq!(env["x"], 12);
+    }
+}
@@ -1,155 +1,454 @@
-mod evaluator;
-mod parser;
-mod scanner;
-
-mod enums;
-use std::io::{self, Write};
-use crate::enums::BinOp;
-use crate::enums::Env;
-use crate::enums::FunctionTable;
-use crate::enums::Expr;
-use crate::enums::Declaration;
-use crate::enums::Statement;
-use crate::enums::Syntax;
-use crate::enums::Token;
-
-fn print_eval_result(str: &str, env: &mut Env, ft: &mut FunctionTable) -> () {
-    print!("-----------------------------------------\n");
-    print!("計算対象：{:?}\n", str);
-    print!("スキャン結果：{:?}\n", scanner::scanner(str));
-    print!("パース結果：{:?}\n", parser::parser(scanner::scanner(str)));
-    print!("結果：");
-    evaluator::eval(parser::parser(scanner::scanner(str)), env, ft);
-    print!("環境：{:?}\n", env);
-    print!("関数テーブル：{:?}\n", ft);
-    print!("-----------------------------------------\n");
-}
+// 既存コードの書き方（改行付き print! など）に合わせるため許可する
+#![allow(clippy::print_with_newline)]
+// Error はトークンをそのまま持つので大きくなる
+#![allow(clippy::result_large_err)]
 
-fn main() {
-    #[cfg(feature = "dhat-heap")]
-    #[global_allocator]
-    static ALLOC: dhat::Alloc = dhat::Alloc;
-    let mut env = Env::new();
-    let mut ft = FunctionTable::new();
+mod editor;
 
-    loop {
-        print!("> ");
-        io::stdout().flush().unwrap();
+use calculator::parser;
+use calculator::scanner;
+use calculator::CancelToken;
+use calculator::Declaration;
+use calculator::Error;
+use calculator::Interpreter;
+use calculator::Value;
+use editor::{Input, LineReader};
+use std::io::{self, IsTerminal, Read};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).ok();
+// 終了コード
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_PARSE_ERROR: i32 = 1;
+const EXIT_RUNTIME_ERROR: i32 = 2;
+// 引数の誤りやファイルが読めない場合
+const EXIT_USAGE: i32 = 3;
+// 評価中でないときに Ctrl-C が押された場合 (128 + SIGINT)
+const EXIT_INTERRUPTED: i32 = 130;
 
-        if input.trim() == "exit" {
-            break;
-        }
+const USAGE: &str = "使い方: calculator [--quiet | --trace] [-i | -e 式 | ファイル]";
 
-        print_eval_result(&input.trim(), &mut env, &mut ft);
-    }
+// REPL のコマンドと説明
+const COMMANDS: &[(&str, &str)] = &[
+    (":vars", "変数の一覧を表示する"),
+    (":funcs", "関数の一覧を表示する"),
+    (":reset", "変数と定義した関数をすべて消す"),
+    (":load <file>", "スクリプトを読み込んで実行する"),
+    (":tokens <expr>", "字句解析の結果を表示する"),
+    (":ast <expr>", "構文解析の結果を表示する"),
+    (":time <expr>", "評価にかかった時間を表示する"),
+    (":debug", "各行の途中経過の表示を切り替える"),
+    (":help", "この一覧を表示する"),
+    ("exit", "終了する"),
+];
 
-    #[cfg(feature = "dhat-heap")]
-    let _profiler = dhat::Profiler::new_heap();
+// 文を評価している間だけ true。Ctrl-C で評価を中断するか終了するかを決める
+static EVALUATING: AtomicBool = AtomicBool::new(false);
+
+// コマンドライン引数
+struct Options {
+    // 式文の値 (プログラムの出力) だけを表示する。プロンプトも出さない
+    quiet: bool,
+    // 各行のトークン列、構文木、環境、関数テーブルも表示する
+    trace: bool,
+    // 標準入力がパイプでもスクリプトではなく REPL として読む
+    interactive: bool,
+    // -e で渡された式
+    expr: Option<String>,
+    // 実行するスクリプトファイル
+    file: Option<String>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        quiet: false,
+        trace: false,
+        interactive: false,
+        expr: None,
+        file: None,
+    };
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-q" | "--quiet" => options.quiet = true,
+            "-t" | "--trace" => options.trace = true,
+            "-i" | "--interactive" => options.interactive = true,
+            "-e" => match args.next() {
+                Some(expr) => options.expr = Some(expr),
+                None => return Err("-e の後に式が必要".to_string()),
+            },
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if arg.starts_with('-') => return Err(format!("不明なオプション {}", arg)),
+            _ => options.file = Some(arg),
+        }
+    }
+    if [options.expr.is_some(), options.file.is_some(), options.interactive].iter().filter(|b| **b).count() > 1 {
+        return Err("-i、-e、ファイルは同時に指定できない".to_string());
+    }
+    if options.quiet && options.trace {
+        return Err("--quiet と --trace は同時に指定できない".to_string());
+    }
+    Ok(options)
+}
 
-    #[test]
-    fn test_assign() {
-        let str = "x = 123";
-        let mut env = Env::new();
-        let mut ft = FunctionTable::new();
-
-        // 実行後に x = 123 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], 123);
+fn exit_code(e: &Error) -> i32 {
+    match e {
+        Error::Parse(_) => EXIT_PARSE_ERROR,
+        Error::Runtime(_) => EXIT_RUNTIME_ERROR,
     }
+}
 
-    #[test]
-    fn test_negative() {
-        let str = "x = -1";
-        let mut env = Env::new();
-        let mut ft = FunctionTable::new();
-
-        // 実行後に x = -1 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], -1);
+// 入力の途中で終わっているために起きたエラーか
+fn is_incomplete(result: &Result<Value, Error>) -> bool {
+    matches!(result, Err(Error::Parse(e)) if e.is_incomplete())
+}
+
+// 1 文を字句解析・構文解析して評価する
+// trace ならトークン列、構文木、評価後の環境と関数テーブルも表示する
+// 文が途中で終わっている場合は何も表示せずにエラーを返すので、続きを足して呼び直せる
+fn eval_line(str: &str, interpreter: &mut Interpreter, trace: bool) -> Result<Value, Error> {
+    let tokens = scanner::scanner(str)?;
+    let syntax = match parser::parser(tokens.clone()) {
+        Err(e) if e.is_incomplete() => return Err(e.into()),
+        syntax => syntax,
+    };
+    if trace {
+        print!("-----------------------------------------\n");
+        print!("計算対象：{:?}\n", str);
+        print!("スキャン結果：{:?}\n", tokens);
+    }
+    let syntax = syntax?;
+    if trace {
+        print!("パース結果：{:?}\n", syntax);
+        print!("結果：");
     }
+    EVALUATING.store(true, Ordering::SeqCst);
+    let result = interpreter.eval(syntax);
+    EVALUATING.store(false, Ordering::SeqCst);
+    if trace {
+        // エラーは呼び出し元が表示する
+        if result.is_err() {
+            print!("\n");
+        }
+        print!("環境：{:?}\n", interpreter.env());
+        print!("関数テーブル：{:?}\n", interpreter.functions());
+        print!("-----------------------------------------\n");
+    }
+    Ok(result?)
+}
 
-    #[test]
-    fn test_if() {
-        let str = "if 0 { x = 2 } else { x = 3 }";
-        let mut env = Env::new();
-        let mut ft = FunctionTable::new();
-
-        // 実行後に x = 3 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], 3);
+// スクリプトを 1 文ずつ実行し、最初のエラーで止めて終了コードを返す
+// 文が途中で終わっている行は次の行と合わせて 1 文にする
+fn run_script(src: &str, interpreter: &mut Interpreter, trace: bool) -> i32 {
+    let mut statement = String::new();
+    // 読みかけの文が始まった行
+    let mut start = 0;
+    for (i, line) in src.lines().enumerate() {
+        if statement.is_empty() {
+            start = i + 1;
+        }
+        if line.trim().is_empty() && statement.is_empty() {
+            continue;
+        }
+        statement.push_str(line);
+        statement.push('\n');
+
+        let result = eval_line(statement.trim(), interpreter, trace);
+        if is_incomplete(&result) {
+            continue;
+        }
+        statement.clear();
+        if let Err(e) = result {
+            eprint!("{} 行目：エラー：{:?}\n", start, e);
+            return exit_code(&e);
+        }
+    }
+    // 最後まで読んでも文が終わらなかった
+    if !statement.trim().is_empty() {
+        if let Err(e) = eval_line(statement.trim(), interpreter, trace) {
+            eprint!("{} 行目：エラー：{:?}\n", start, e);
+            return exit_code(&e);
+        }
     }
+    EXIT_SUCCESS
+}
 
-    #[test]
-    fn test_addition() {
-        let str = "x = 1 + 2 + 3";
-        let mut env = Env::new();
-        let mut ft = FunctionTable::new();
-
-        // 実行後に x = 6 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], 6);
+// REPL で表示した式文の値の履歴
+// 直前の値を ans と _ に、n 番目の値を _n に代入して次の行から使えるようにする
+struct ResultHistory {
+    // 出力先で受け取って、まだ代入していない値
+    pending: Arc<Mutex<Vec<Value>>>,
+    // これまでに代入した値の数
+    count: usize,
+}
+
+impl ResultHistory {
+    // 式文の値を表示しながら履歴にも受け取るように出力先を設定する
+    fn new(interpreter: &mut Interpreter) -> Self {
+        let pending = Arc::new(Mutex::new(Vec::new()));
+        let sink = pending.clone();
+        interpreter.evaluator_mut().set_output(move |value| {
+            println!("{}", value);
+            sink.lock().unwrap().push(value.clone());
+        });
+        ResultHistory { pending, count: 0 }
     }
 
-    #[test]
-    fn test_subtraction() {
-        let str = "x = 1 - 2 - 3";
-        let mut env = Env::new();
-        let mut ft = FunctionTable::new();
-
-        // 実行後に x = -4 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], -4);
+    // 受け取った値を順に変数に代入する
+    fn bind(&mut self, interpreter: &mut Interpreter) {
+        for value in self.pending.lock().unwrap().drain(..) {
+            self.count += 1;
+            interpreter.set_var(&format!("_{}", self.count), value.clone());
+            interpreter.set_var("_", value.clone());
+            interpreter.set_var("ans", value);
+        }
     }
 
-    #[test]
-    fn test_multiplication() {
-        let str = "x = 1 * 2 * 3";
-        let mut env = Env::new();
-        let mut ft = FunctionTable::new();
-
-        // 実行後に x = 6 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], 6);
+    // :reset の後は _1 から数え直す
+    fn reset(&mut self) {
+        self.pending.lock().unwrap().clear();
+        self.count = 0;
     }
+}
 
-    #[test]
-    fn test_division() {
-        let str = "x = 4 / 2 / 2";
-        let mut env = Env::new();
-        let mut ft = FunctionTable::new();
-
-        // 実行後に x = 1 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], 1);
+// : で始まる REPL のコマンドを実行する
+fn command(input: &str, interpreter: &mut Interpreter, trace: &mut bool, history: &mut ResultHistory) {
+    let (name, arg) = match input.split_once(' ') {
+        Some((name, arg)) => (name, arg.trim()),
+        None => (input, ""),
+    };
+    match name {
+        ":vars" => {
+            let mut vars: Vec<_> = interpreter.env().iter().collect();
+            vars.sort_by(|a, b| a.0.cmp(b.0));
+            for (name, value) in vars {
+                print!("{} = {}\n", name, value);
+            }
+        }
+        ":funcs" => {
+            let mut funcs: Vec<_> = interpreter.functions().iter().collect();
+            funcs.sort_by(|a, b| a.0.cmp(b.0));
+            for (name, declaration) in funcs {
+                match declaration {
+                    Declaration::Function { params, .. } => print!("{}({})\n", name, params.join(", ")),
+                    Declaration::Native { arity, .. } => print!("{}/{} (組み込み先の関数)\n", name, arity),
+                }
+            }
+        }
+        ":reset" => {
+            interpreter.reset();
+            history.reset();
+        }
+        ":load" => match std::fs::read_to_string(arg) {
+            Ok(src) => {
+                run_script(&src, interpreter, *trace);
+            }
+            Err(e) => eprint!("{} を読めない：{}\n", arg, e),
+        },
+        ":tokens" => match scanner::scanner(arg) {
+            Ok(tokens) => print!("{:?}\n", tokens),
+            Err(e) => eprint!("エラー：{:?}\n", e),
+        },
+        ":ast" => match scanner::scanner(arg).and_then(parser::parser) {
+            Ok(syntax) => print!("{:#?}\n", syntax),
+            Err(e) => eprint!("エラー：{:?}\n", e),
+        },
+        ":time" => {
+            let start = Instant::now();
+            let result = eval_line(arg, interpreter, *trace);
+            let elapsed = start.elapsed();
+            if let Err(e) = result {
+                eprint!("エラー：{:?}\n", e);
+            }
+            print!("時間：{:?}\n", elapsed);
+        }
+        ":debug" => {
+            *trace = !*trace;
+            print!("デバッグ表示：{}\n", if *trace { "オン" } else { "オフ" });
+        }
+        ":help" => {
+            for (command, description) in COMMANDS {
+                print!("{:<16}{}\n", command, description);
+            }
+        }
+        _ => eprint!("不明なコマンド {}。:help で一覧を表示する\n", name),
     }
+}
 
-    #[test]
-    fn test_parenthesis() {
-        let str = "x = 2 * (3 + 4) ";
-        let mut env = Env::new();
-        let mut ft = FunctionTable::new();
-
-        // 実行後に x = 14 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], 14);
+fn repl(interpreter: &mut Interpreter, token: &CancelToken, options: &Options) {
+    let mut trace = options.trace;
+    let mut history = ResultHistory::new(interpreter);
+    let mut reader = LineReader::new();
+    // 端末から読む場合だけプロンプトを出す
+    let prompt = !options.quiet && reader.is_terminal();
+    // 複数行にわたって入力中の文
+    let mut statement = String::new();
+    loop {
+        let prompt = match (prompt, statement.is_empty()) {
+            (false, _) => "",
+            (true, true) => "> ",
+            (true, false) => "... ",
+        };
+        reader.update_completion(interpreter);
+        let input = match reader.read_line(prompt) {
+            Ok(Input::Line(input)) => input,
+            // 入力中の Ctrl-C は入力途中の文を捨てる
+            Ok(Input::Interrupted) => {
+                statement.clear();
+                continue;
+            }
+            Ok(Input::Eof) => {
+                // EOF (Ctrl-D)。入力中の文があれば評価してから終了する
+                if !statement.is_empty() {
+                    if let Err(e) = eval_line(statement.trim(), interpreter, trace) {
+                        eprint!("エラー：{:?}\n", e);
+                    }
+                }
+                break;
+            }
+            Err(e) => {
+                eprint!("標準入力を読めない：{}\n", e);
+                break;
+            }
+        };
+
+        if statement.is_empty() {
+            if input.trim() == "exit" {
+                break;
+            }
+            if input.trim().is_empty() {
+                continue;
+            }
+        }
+
+        // 入力待ちの間に押された Ctrl-C は無視する
+        token.reset();
+        if statement.is_empty() && input.trim().starts_with(':') {
+            reader.add_history(&input);
+            command(input.trim(), interpreter, &mut trace, &mut history);
+            history.bind(interpreter);
+            continue;
+        }
+
+        // 続きの入力中に空行が入力されたら、そこまでで評価してエラーを表示する
+        let finish = !statement.is_empty() && input.trim().is_empty();
+        statement.push_str(&input);
+        statement.push('\n');
+        let result = eval_line(statement.trim(), interpreter, trace);
+        if is_incomplete(&result) && !finish {
+            continue;
+        }
+        reader.add_history(&statement);
+        statement.clear();
+        // エラーになった行でもそれまでに表示した値は履歴に残す
+        history.bind(interpreter);
+        // エラーを表示して続けて次の行を読む
+        if let Err(e) = result {
+            eprint!("エラー：{:?}\n", e);
+        }
     }
+    reader.save_history();
+}
+
+fn main() {
+    #[cfg(feature = "dhat-heap")]
+    #[global_allocator]
+    static ALLOC: dhat::Alloc = dhat::Alloc;
+
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprint!("{}\n{}\n", message, USAGE);
+            process::exit(EXIT_USAGE);
+        }
+    };
+
+    let mut interpreter = Interpreter::new();
+    // 式文の値はその場で表示する
+    interpreter.evaluator_mut().set_output(|value| println!("{}", value));
+
+    // 評価中の Ctrl-C では終了せず、実行中の文だけを中断して env と ft を残す
+    // 標準入力を待っている間などの Ctrl-C では通常どおり終了する
+    // (端末の行編集中の Ctrl-C は rustyline がキー入力として受け取る)
+    let token = CancelToken::new();
+    let handler_token = token.clone();
+    ctrlc::set_handler(move || {
+        if EVALUATING.load(Ordering::SeqCst) {
+            handler_token.cancel();
+        } else {
+            process::exit(EXIT_INTERRUPTED);
+        }
+    })
+    .expect("Ctrl-C ハンドラを登録できなかった");
+    interpreter.evaluator_mut().set_cancel_token(token.clone());
+
+    // -e の式、ファイル、パイプで渡された標準入力の順に実行するものを決める
+    let script = match (&options.expr, &options.file) {
+        (Some(expr), _) => Some(expr.clone()),
+        (None, Some(file)) => match std::fs::read_to_string(file) {
+            Ok(src) => Some(src),
+            Err(e) => {
+                eprint!("{} を読めない：{}\n", file, e);
+                process::exit(EXIT_USAGE);
+            }
+        },
+        (None, None) if !options.interactive && !io::stdin().is_terminal() => {
+            let mut src = String::new();
+            if let Err(e) = io::stdin().read_to_string(&mut src) {
+                eprint!("標準入力を読めない：{}\n", e);
+                process::exit(EXIT_USAGE);
+            }
+            Some(src)
+        }
+        (None, None) => None,
+    };
+
+    let code = match script {
+        Some(src) => run_script(&src, &mut interpreter, options.trace),
+        None => {
+            repl(&mut interpreter, &token, &options);
+            EXIT_SUCCESS
+        }
+    };
+
+    #[cfg(feature = "dhat-heap")]
+    let _profiler = dhat::Profiler::new_heap();
+
+    process::exit(code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
-    fn test_compound_statement() {
-        let str = "if 0 { x = 0 } else { x = 1 } ; if x { x = 3 } else { x = 4 }";
-        let mut env = Env::new();
-        let mut ft = FunctionTable::new();
-
-        // 実行後に x = 3 が代入されていること
-        evaluator::eval(parser::parser(scanner::scanner(str)), &mut env, &mut ft);
-        assert_eq!(env["x"], 3);
+    fn test_result_history() {
+        let mut interpreter = Interpreter::new();
+        let mut history = ResultHistory::new(&mut interpreter);
+        let mut trace = false;
+
+        // 表示した値が順に _1, _2 に、最後の値が ans と _ に代入されること
+        interpreter.eval_str("1 + 1 ; x = 10 ; 3").unwrap();
+        history.bind(&mut interpreter);
+        assert_eq!(interpreter.get_var("_1"), Some(&Value::Int(2)));
+        assert_eq!(interpreter.get_var("_2"), Some(&Value::Int(3)));
+        assert_eq!(interpreter.get_var("ans"), Some(&Value::Int(3)));
+        assert_eq!(interpreter.get_var("_"), Some(&Value::Int(3)));
+
+        // 次の行から ans を使え、番号が続くこと
+        interpreter.eval_str("ans * _1").unwrap();
+        history.bind(&mut interpreter);
+        assert_eq!(interpreter.get_var("_3"), Some(&Value::Int(6)));
+        assert_eq!(interpreter.get_var("ans"), Some(&Value::Int(6)));
+
+        // :reset の後は _1 から数え直すこと
+        command(":reset", &mut interpreter, &mut trace, &mut history);
+        interpreter.eval_str("5").unwrap();
+        history.bind(&mut interpreter);
+        assert_eq!(interpreter.get_var("_1"), Some(&Value::Int(5)));
+        assert_eq!(interpreter.get_var("_2"), None);
+        assert_eq!(interpreter.get_var("ans"), Some(&Value::Int(5)));
     }
 }
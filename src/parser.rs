@@ -1,8 +1,17 @@
 mod addexpr;
+mod bitandexpr;
+mod bitorexpr;
+mod bitxorexpr;
 mod mulexpr;
+mod postfixexpr;
+mod powexpr;
 mod primaryexpr;
+mod shiftexpr;
 mod state;
+mod unaryexpr;
 
+use crate::ParseError;
+use crate::Statement;
 use crate::Syntax;
 use crate::Token;
 
@@ -37,15 +46,36 @@ impl Parser {
         self.pos += 1;
     }
 
-    fn confirm(&mut self, expect: Token) {
+    fn confirm(&mut self, expect: Token) -> Result<(), ParseError> {
         match self.current() {
-            Some(token) if token == expect => self.fix(),
-            _ => panic!("{:?} を想定しているところに想定外のトークン {:?} がきた", expect, self.current()),
-        };
+            Some(token) if token == expect => {
+                self.fix();
+                Ok(())
+            }
+            found => Err(ParseError::Expected { expected: expect, found }),
+        }
+    }
+}
+
+impl ParseError {
+    /// 入力の途中で終わっているために起きたエラーか
+    /// 閉じていない括弧、末尾の演算子、ブロックの無い if などが該当し、続きを入力すれば解析できる
+    pub fn is_incomplete(&self) -> bool {
+        matches!(self, ParseError::Expected { found: None, .. } | ParseError::Unexpected(None))
     }
 }
 
-pub fn parser(toks: Vec<Token>) -> Syntax {
+pub fn parser(toks: Vec<Token>) -> Result<Syntax, ParseError> {
+    // 空の入力は何もしない文にする
+    if toks.is_empty() {
+        return Ok(Syntax::Statement(Statement::Null));
+    }
+
     let mut parser = Parser::new(toks);
-    return Syntax::Statement(parser.state());
+    let statement = parser.state()?;
+    // 文の後に読み残したトークンがあればエラーにする
+    if let Some(token) = parser.current() {
+        return Err(ParseError::Unexpected(Some(token)));
+    }
+    return Ok(Syntax::Statement(statement));
 }
@@ -1,6 +1,7 @@
 use crate::parser::Parser;
 use crate::BinOp;
 use crate::Expr;
+use crate::ParseError;
 use crate::Token;
 
 impl Parser {
@@ -8,39 +9,39 @@ impl Parser {
     /// AddExpr = MulExpr { AddOp MulExpr }
     /// AddOp = '+' | '-'
     ///
-    pub(crate) fn add(&mut self) -> Expr {
-        let mut expr = self.mul();
+    pub(crate) fn add(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.mul()?;
         loop {
             match self.current() {
                 Some(Token::PLUS) => {
-                    expr = self.plus(expr);
+                    expr = self.plus(expr)?;
                 }
                 Some(Token::MINUS) => {
-                    expr = self.minus(expr);
+                    expr = self.minus(expr)?;
                 }
                 _ => {
                     break;
                 }
             }
         }
-        expr
+        Ok(expr)
     }
 
-    fn plus(&mut self, lhs: Expr) -> Expr {
-        self.confirm(Token::PLUS);
-        Expr::Binary {
+    fn plus(&mut self, lhs: Expr) -> Result<Expr, ParseError> {
+        self.confirm(Token::PLUS)?;
+        Ok(Expr::Binary {
             op: BinOp::Add,
             lhs: Box::new(lhs),
-            rhs: Box::new(self.mul()),
-        }
+            rhs: Box::new(self.mul()?),
+        })
     }
 
-    fn minus(&mut self, lhs: Expr) -> Expr {
-        self.confirm(Token::MINUS);
-        Expr::Binary {
+    fn minus(&mut self, lhs: Expr) -> Result<Expr, ParseError> {
+        self.confirm(Token::MINUS)?;
+        Ok(Expr::Binary {
             op: BinOp::Sub,
             lhs: Box::new(lhs),
-            rhs: Box::new(self.mul()),
-        }
+            rhs: Box::new(self.mul()?),
+        })
     }
 }
@@ -0,0 +1,27 @@
+use crate::parser::Parser;
+use crate::BinOp;
+use crate::Expr;
+use crate::ParseError;
+use crate::Token;
+
+impl Parser {
+    ///
+    /// BitAndExpr = ShiftExpr { '&' ShiftExpr }
+    ///
+    pub(crate) fn bitand(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.shift()?;
+        while let Some(Token::AMP) = self.current() {
+            expr = self.amp(expr)?;
+        }
+        Ok(expr)
+    }
+
+    fn amp(&mut self, lhs: Expr) -> Result<Expr, ParseError> {
+        self.confirm(Token::AMP)?;
+        Ok(Expr::Binary {
+            op: BinOp::BitAnd,
+            lhs: Box::new(lhs),
+            rhs: Box::new(self.shift()?),
+        })
+    }
+}
@@ -0,0 +1,27 @@
+use crate::parser::Parser;
+use crate::BinOp;
+use crate::Expr;
+use crate::ParseError;
+use crate::Token;
+
+impl Parser {
+    ///
+    /// BitOrExpr = BitXorExpr { '|' BitXorExpr }
+    ///
+    pub(crate) fn bitor(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.bitxor()?;
+        while let Some(Token::PIPE) = self.current() {
+            expr = self.pipe(expr)?;
+        }
+        Ok(expr)
+    }
+
+    fn pipe(&mut self, lhs: Expr) -> Result<Expr, ParseError> {
+        self.confirm(Token::PIPE)?;
+        Ok(Expr::Binary {
+            op: BinOp::BitOr,
+            lhs: Box::new(lhs),
+            rhs: Box::new(self.bitxor()?),
+        })
+    }
+}
@@ -0,0 +1,27 @@
+use crate::parser::Parser;
+use crate::BinOp;
+use crate::Expr;
+use crate::ParseError;
+use crate::Token;
+
+impl Parser {
+    ///
+    /// BitXorExpr = BitAndExpr { 'xor' BitAndExpr }
+    ///
+    pub(crate) fn bitxor(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.bitand()?;
+        while let Some(Token::XOR) = self.current() {
+            expr = self.xor(expr)?;
+        }
+        Ok(expr)
+    }
+
+    fn xor(&mut self, lhs: Expr) -> Result<Expr, ParseError> {
+        self.confirm(Token::XOR)?;
+        Ok(Expr::Binary {
+            op: BinOp::BitXor,
+            lhs: Box::new(lhs),
+            rhs: Box::new(self.bitand()?),
+        })
+    }
+}
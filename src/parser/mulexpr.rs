@@ -1,46 +1,73 @@
 use crate::parser::Parser;
 use crate::BinOp;
 use crate::Expr;
+use crate::ParseError;
 use crate::Token;
 
 impl Parser {
     ///
-    /// MulExpr = PrimaryExpr { MulOp PrimaryExpr }
-    /// MulOp = '*' | '/'
+    /// MulExpr = UnaryExpr { MulOp UnaryExpr | PowerExpr }
+    /// MulOp = '*' | '/' | '%'
     ///
-    pub(crate) fn mul(&mut self) -> Expr {
-        let mut expr = self.primary();
+    /// 演算子を挟まずに識別子や '(' が続く場合は掛け算とみなす (2x, 3(4+5), (a+b)(a-b))
+    /// 優先順位は * と同じで左結合 (6/2x = (6/2)x)。f(x) は PrimaryExpr で関数呼び出しとして読まれる
+    ///
+    pub(crate) fn mul(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.unary()?;
         loop {
             match self.current() {
                 Some(Token::STAR) => {
-                    expr = self.star(expr);
+                    expr = self.star(expr)?;
                 }
                 Some(Token::SLASH) => {
-                    expr = self.slash(expr);
+                    expr = self.slash(expr)?;
+                }
+                Some(Token::PERCENT) => {
+                    expr = self.percent(expr)?;
+                }
+                Some(Token::IDENT(_)) | Some(Token::LPAR) => {
+                    expr = self.implicit(expr)?;
                 }
                 _ => {
                     break;
                 }
             }
         }
-        expr
+        Ok(expr)
     }
 
-    fn star(&mut self, lhs: Expr) -> Expr {
-        self.confirm(Token::STAR);
-        Expr::Binary {
+    fn star(&mut self, lhs: Expr) -> Result<Expr, ParseError> {
+        self.confirm(Token::STAR)?;
+        Ok(Expr::Binary {
             op: BinOp::Mul,
             lhs: Box::new(lhs),
-            rhs: Box::new(self.primary()),
-        }
+            rhs: Box::new(self.unary()?),
+        })
     }
 
-    fn slash(&mut self, lhs: Expr) -> Expr {
-        self.confirm(Token::SLASH);
-        Expr::Binary {
+    fn slash(&mut self, lhs: Expr) -> Result<Expr, ParseError> {
+        self.confirm(Token::SLASH)?;
+        Ok(Expr::Binary {
             op: BinOp::Div,
             lhs: Box::new(lhs),
-            rhs: Box::new(self.primary()),
-        }
+            rhs: Box::new(self.unary()?),
+        })
+    }
+
+    fn percent(&mut self, lhs: Expr) -> Result<Expr, ParseError> {
+        self.confirm(Token::PERCENT)?;
+        Ok(Expr::Binary {
+            op: BinOp::Rem,
+            lhs: Box::new(lhs),
+            rhs: Box::new(self.unary()?),
+        })
+    }
+
+    fn implicit(&mut self, lhs: Expr) -> Result<Expr, ParseError> {
+        Ok(Expr::Binary {
+            op: BinOp::Mul,
+            lhs: Box::new(lhs),
+            rhs: Box::new(self.power()?),
+        })
     }
 }
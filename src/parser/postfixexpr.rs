@@ -0,0 +1,59 @@
+use crate::parser::Parser;
+use crate::Expr;
+use crate::ParseError;
+use crate::Token;
+use crate::UnOp;
+
+impl Parser {
+    ///
+    /// PostfixExpr = PrimaryExpr { '!' | '%' }
+    ///
+    /// '!' は後置の場合だけ階乗として扱い、前置の '!' は論理否定のために空けておく
+    /// '%' の後に項が始まる場合 (NUMBER, ID, '(', '~') は MulExpr の剰余として扱う
+    /// '+' '-' が続く場合はパーセントの後の足し算・引き算になるので、負の数の剰余は 7 % (-2) と書く
+    ///
+    pub(crate) fn postfix(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.primary()?;
+        loop {
+            match self.current() {
+                Some(Token::BANG) => {
+                    expr = self.factorial(expr)?;
+                }
+                Some(Token::PERCENT) if !self.operand_follows() => {
+                    expr = self.percent_of(expr)?;
+                }
+                _ => {
+                    break;
+                }
+            }
+        }
+        Ok(expr)
+    }
+
+    // 次のトークンが項の始まりか
+    fn operand_follows(&self) -> bool {
+        matches!(
+            self.next(),
+            Some(Token::NUMBER(_))
+                | Some(Token::IDENT(_))
+                | Some(Token::LPAR)
+                | Some(Token::TILDE)
+        )
+    }
+
+    fn factorial(&mut self, expr: Expr) -> Result<Expr, ParseError> {
+        self.confirm(Token::BANG)?;
+        Ok(Expr::Unary {
+            op: UnOp::Factorial,
+            expr: Box::new(expr),
+        })
+    }
+
+    fn percent_of(&mut self, expr: Expr) -> Result<Expr, ParseError> {
+        self.confirm(Token::PERCENT)?;
+        Ok(Expr::Unary {
+            op: UnOp::Percent,
+            expr: Box::new(expr),
+        })
+    }
+}
@@ -0,0 +1,29 @@
+use crate::parser::Parser;
+use crate::BinOp;
+use crate::Expr;
+use crate::ParseError;
+use crate::Token;
+
+impl Parser {
+    ///
+    /// PowerExpr = PostfixExpr [ '^' UnaryExpr ]
+    ///
+    /// 右結合で単項マイナスより強く結合する (-2^2 = -4, 2^3^2 = 2^9)
+    ///
+    pub(crate) fn power(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.postfix()?;
+        match self.current() {
+            Some(Token::CARET) => self.caret(expr),
+            _ => Ok(expr),
+        }
+    }
+
+    fn caret(&mut self, lhs: Expr) -> Result<Expr, ParseError> {
+        self.confirm(Token::CARET)?;
+        Ok(Expr::Binary {
+            op: BinOp::Pow,
+            lhs: Box::new(lhs),
+            rhs: Box::new(self.unary()?),
+        })
+    }
+}
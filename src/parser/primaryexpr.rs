@@ -1,77 +1,65 @@
 use crate::parser::Parser;
 use crate::Expr;
+use crate::ParseError;
 use crate::Token;
+use crate::Value;
 
 impl Parser {
     ///
-    /// PrimaryExpr = '(' AddExpr ')' | NUMBER | ID
+    /// PrimaryExpr = '(' BitOrExpr ')' | '{' BitOrExpr '}' | NUMBER | ID [ '(' [ BitOrExpr { ',' BitOrExpr } ] ')' ]
     ///
-    pub(crate) fn primary(&mut self) -> Expr {
+    pub(crate) fn primary(&mut self) -> Result<Expr, ParseError> {
         return match self.current() {
             Some(Token::LPAR) => self.par(),
             Some(Token::LBRACE) => self.brace(),
             Some(Token::NUMBER(n)) => self.number(n),
             Some(Token::IDENT(str)) => self.ident(str),
-            Some(Token::MINUS) => self.negative(),
-            _ => {
-                panic!("PrimaryExpr を判定する際に想定外のトークン {:?} がきた", self.current());
-            }
+            token => Err(ParseError::Unexpected(token)),
         };
     }
 
-    fn par(&mut self) -> Expr {
-        self.confirm(Token::LPAR);
-        let result = self.expr();
-        self.confirm(Token::RPAR);
-        result
+    fn par(&mut self) -> Result<Expr, ParseError> {
+        self.confirm(Token::LPAR)?;
+        let result = self.expr()?;
+        self.confirm(Token::RPAR)?;
+        Ok(result)
     }
 
-    fn brace(&mut self) -> Expr {
-        self.confirm(Token::LBRACE);
-        let result = self.expr();
-        self.confirm(Token::RBRACE);
-        result
+    fn brace(&mut self) -> Result<Expr, ParseError> {
+        self.confirm(Token::LBRACE)?;
+        let result = self.expr()?;
+        self.confirm(Token::RBRACE)?;
+        Ok(result)
     }
 
-    fn number(&mut self, n: i32) -> Expr {
-        self.confirm(Token::NUMBER(n));
-        Expr::Number(n)
+    fn number(&mut self, n: Value) -> Result<Expr, ParseError> {
+        self.confirm(Token::NUMBER(n.clone()))?;
+        Ok(Expr::Number(n))
     }
 
-    fn ident(&mut self, str: String) -> Expr {
+    fn ident(&mut self, str: String) -> Result<Expr, ParseError> {
         self.fix();
         match self.current() {
             Some(Token::LPAR) => {
-                self.confirm(Token::LPAR);
+                self.confirm(Token::LPAR)?;
                 let mut args = vec![];
 
-                args.push(self.expr());
-                while let Some(Token::COMMA) = self.current() {
-                    self.confirm(Token::COMMA);
-                    args.push(self.expr());
+                // 引数の無い呼び出し name() も受け付ける
+                if self.current() != Some(Token::RPAR) {
+                    args.push(self.expr()?);
+                    while let Some(Token::COMMA) = self.current() {
+                        self.confirm(Token::COMMA)?;
+                        args.push(self.expr()?);
+                    }
                 }
 
-                self.confirm(Token::RPAR);
-                Expr::FunctionCall {
+                self.confirm(Token::RPAR)?;
+                Ok(Expr::FunctionCall {
                     id: str,
                     args: args,
-                }
-            }
-            _ => Expr::Var(str),
-        }
-    }
-
-    // TODO 負の数考え中
-    fn negative(&mut self) -> Expr {
-        self.confirm(Token::MINUS);
-        match self.current() {
-            Some(Token::NUMBER(n)) => {
-                self.fix();
-                Expr::Number(-1 * n)
-            }
-            _ => {
-                panic!("PrimaryExpr を判定する際に想定外のトークンがきた");
+                })
             }
+            _ => Ok(Expr::Var(str)),
         }
     }
 }
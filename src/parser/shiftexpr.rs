@@ -0,0 +1,47 @@
+use crate::parser::Parser;
+use crate::BinOp;
+use crate::Expr;
+use crate::ParseError;
+use crate::Token;
+
+impl Parser {
+    ///
+    /// ShiftExpr = AddExpr { ShiftOp AddExpr }
+    /// ShiftOp = '<<' | '>>'
+    ///
+    pub(crate) fn shift(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.add()?;
+        loop {
+            match self.current() {
+                Some(Token::SHL) => {
+                    expr = self.shl(expr)?;
+                }
+                Some(Token::SHR) => {
+                    expr = self.shr(expr)?;
+                }
+                _ => {
+                    break;
+                }
+            }
+        }
+        Ok(expr)
+    }
+
+    fn shl(&mut self, lhs: Expr) -> Result<Expr, ParseError> {
+        self.confirm(Token::SHL)?;
+        Ok(Expr::Binary {
+            op: BinOp::Shl,
+            lhs: Box::new(lhs),
+            rhs: Box::new(self.add()?),
+        })
+    }
+
+    fn shr(&mut self, lhs: Expr) -> Result<Expr, ParseError> {
+        self.confirm(Token::SHR)?;
+        Ok(Expr::Binary {
+            op: BinOp::Shr,
+            lhs: Box::new(lhs),
+            rhs: Box::new(self.add()?),
+        })
+    }
+}
@@ -1,5 +1,6 @@
 use crate::parser::Parser;
 use crate::Expr;
+use crate::ParseError;
 use crate::Statement;
 use crate::Token;
 
@@ -7,47 +8,58 @@ impl Parser {
     ///
     /// Statement = IfStatement | AssignStatement | CompoundStatement
     /// CompoundStatement = Statement { ';' Statement }
+    /// AssignStatement = ID '=' Expr | ID '(' [ ID { ',' ID } ] ')' '=' Expr
     ///
-    pub(crate) fn state(&mut self) -> Statement {
+    pub(crate) fn state(&mut self) -> Result<Statement, ParseError> {
         let result = match self.current() {
-            Some(Token::FN) => self.function_define_statement(), 
-            Some(Token::RETURN) => self.return_statement(), 
-            Some(Token::IF) => self.if_statement(),
+            Some(Token::FN) => self.function_define_statement()?, 
+            Some(Token::RETURN) => self.return_statement()?, 
+            Some(Token::IF) => self.if_statement()?,
             Some(Token::IDENT(s)) => 
                 match self.next() {
-                    Some(Token::EQ) => self.ident_statement(s), 
-                    _ => self.expr_statement()
+                    Some(Token::EQ) => self.ident_statement(s)?, 
+                    Some(Token::LPAR) if self.function_shorthand_follows() => self.ident_statement(s)?,
+                    _ => self.expr_statement()?
                 }
-            _ => self.expr_statement(),
+            _ => self.expr_statement()?,
         };
 
         // 文の後に ';' が続くようであれば次の文を扱う
         match self.current() {
             Some(Token::SEMICOLON) => self.compound_statement(result),
-            _ => result,
+            _ => Ok(result),
         }
     }
 
-    pub(crate) fn expr(&mut self) -> Expr {
-        return self.add();
+    pub(crate) fn expr(&mut self) -> Result<Expr, ParseError> {
+        return self.bitor();
     }
 
-    fn print(&mut self) -> Statement {
-        self.confirm(Token::FN);
-        Statement::Print { expr: self.expr() }
-    }
-
-    fn function_define_statement(&mut self) -> Statement {
-        self.confirm(Token::FN);
+    fn function_define_statement(&mut self) -> Result<Statement, ParseError> {
+        self.confirm(Token::FN)?;
         // name
         let name = match self.current() {
             Some(Token::IDENT(s)) => s,
-            _ => panic!("unexpected identifier"),
+            token => return Err(ParseError::Unexpected(token)),
         };
         self.fix();
 
-        self.confirm(Token::LPAR);
-        // params
+        let params = self.params()?;
+
+        self.confirm(Token::LBRACE)?;
+        // body
+        let body = self.state()?;
+        self.confirm(Token::RBRACE)?;
+        Ok(Statement::FunctionDefine {
+            id: name,
+            params: params,
+            body: Box::new(body),
+        })
+    }
+
+    // 仮引数の並び '(' [ ID { ',' ID } ] ')' を読む
+    fn params(&mut self) -> Result<Vec<String>, ParseError> {
+        self.confirm(Token::LPAR)?;
         let mut params = vec![];
         loop {
             match self.current() {
@@ -63,67 +75,81 @@ impl Parser {
                 }
             }
         }
-        self.confirm(Token::RPAR);
+        self.confirm(Token::RPAR)?;
+        Ok(params)
+    }
 
-        self.confirm(Token::LBRACE);
-        // body
-        let body = self.state();
-        self.confirm(Token::RBRACE);
-        Statement::FunctionDefine {
-            id: name,
-            params: params,
-            body: Box::new(body),
+    // 現在位置から name(a, b) = の形が続くか
+    fn function_shorthand_follows(&self) -> bool {
+        let mut i = self.pos + 2;
+        loop {
+            match self.input.get(i) {
+                Some(Token::IDENT(_)) | Some(Token::COMMA) => i += 1,
+                Some(Token::RPAR) => return self.input.get(i + 1) == Some(&Token::EQ),
+                _ => return false,
+            }
         }
     }
 
-    fn return_statement(&mut self) -> Statement {
-        self.confirm(Token::RETURN);
-        Statement::Return { expr: Box::new(self.expr()) }
+    fn return_statement(&mut self) -> Result<Statement, ParseError> {
+        self.confirm(Token::RETURN)?;
+        Ok(Statement::Return { expr: Box::new(self.expr()?) })
     }
 
-    fn if_statement(&mut self) -> Statement {
-        self.confirm(Token::IF);
-        let expr1 = self.expr();
+    fn if_statement(&mut self) -> Result<Statement, ParseError> {
+        self.confirm(Token::IF)?;
+        let expr1 = self.expr()?;
 
-        self.confirm(Token::LBRACE);
-        let state1 = self.state();
-        self.confirm(Token::RBRACE);
+        self.confirm(Token::LBRACE)?;
+        let state1 = self.state()?;
+        self.confirm(Token::RBRACE)?;
 
-        self.confirm(Token::ELSE);
-        self.confirm(Token::LBRACE);
-        let state2 = self.state();
-        self.confirm(Token::RBRACE);
+        self.confirm(Token::ELSE)?;
+        self.confirm(Token::LBRACE)?;
+        let state2 = self.state()?;
+        self.confirm(Token::RBRACE)?;
 
-        Statement::If {
+        Ok(Statement::If {
             condition: Box::new(expr1),
             then: Box::new(state1),
             els: Box::new(state2),
-        }
+        })
     }
 
-    fn ident_statement(&mut self, s: String) -> Statement {
+    fn ident_statement(&mut self, s: String) -> Result<Statement, ParseError> {
         match self.next() {
             Some(Token::EQ) => {
                 self.fix();
-                self.confirm(Token::EQ);
-                Statement::Assign {
+                self.confirm(Token::EQ)?;
+                Ok(Statement::Assign {
                     id: s,
-                    e: Box::new(self.expr()),
-                }
+                    e: Box::new(self.expr()?),
+                })
             }
-            _ => Statement::Null,
+            // sq(x) = x * x は本体が return x * x だけの関数定義とみなす
+            Some(Token::LPAR) => {
+                self.fix();
+                let params = self.params()?;
+                self.confirm(Token::EQ)?;
+                Ok(Statement::FunctionDefine {
+                    id: s,
+                    params: params,
+                    body: Box::new(Statement::Return { expr: Box::new(self.expr()?) }),
+                })
+            }
+            _ => Ok(Statement::Null),
         }
     }
 
-    fn expr_statement(&mut self) -> Statement {
-        Statement::ExprStatement { expr: self.expr() }
+    fn expr_statement(&mut self) -> Result<Statement, ParseError> {
+        Ok(Statement::ExprStatement { expr: self.expr()? })
     }
 
-    fn compound_statement(&mut self, st: Statement) -> Statement {
-        self.confirm(Token::SEMICOLON);
-        Statement::CompoundStatement {
+    fn compound_statement(&mut self, st: Statement) -> Result<Statement, ParseError> {
+        self.confirm(Token::SEMICOLON)?;
+        Ok(Statement::CompoundStatement {
             st1: Box::new(st),
-            st2: Box::new(self.state()),
-        }
+            st2: Box::new(self.state()?),
+        })
     }
 }
@@ -0,0 +1,45 @@
+use crate::parser::Parser;
+use crate::Expr;
+use crate::ParseError;
+use crate::Token;
+use crate::UnOp;
+
+impl Parser {
+    ///
+    /// UnaryExpr = '-' UnaryExpr | '+' UnaryExpr | '~' UnaryExpr | PowerExpr
+    ///
+    pub(crate) fn unary(&mut self) -> Result<Expr, ParseError> {
+        match self.current() {
+            Some(Token::MINUS) => self.negative(),
+            Some(Token::PLUS) => self.positive(),
+            Some(Token::TILDE) => self.bitnot(),
+            _ => self.power(),
+        }
+    }
+
+    fn negative(&mut self) -> Result<Expr, ParseError> {
+        self.confirm(Token::MINUS)?;
+        match self.unary()? {
+            // 数値リテラルはその場で負の数にする
+            Expr::Number(n) => Ok(Expr::Number(-n)),
+            expr => Ok(Expr::Unary {
+                op: UnOp::Neg,
+                expr: Box::new(expr),
+            }),
+        }
+    }
+
+    // 符号の '+' は値を変えない
+    fn positive(&mut self) -> Result<Expr, ParseError> {
+        self.confirm(Token::PLUS)?;
+        self.unary()
+    }
+
+    fn bitnot(&mut self) -> Result<Expr, ParseError> {
+        self.confirm(Token::TILDE)?;
+        Ok(Expr::Unary {
+            op: UnOp::BitNot,
+            expr: Box::new(self.unary()?),
+        })
+    }
+}
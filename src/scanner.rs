@@ -1,4 +1,17 @@
+use crate::ParseError;
 use crate::Token;
+use crate::Value;
+use num_bigint::BigInt;
+use std::ops::Range;
+
+/// 予約語とそのトークン
+pub const KEYWORDS: &[(&str, Token)] = &[
+    ("if", Token::IF),
+    ("else", Token::ELSE),
+    ("fn", Token::FN),
+    ("return", Token::RETURN),
+    ("xor", Token::XOR),
+];
 
 struct Scanner {
     input: Vec<char>,
@@ -13,56 +26,87 @@ impl Scanner {
     }
 
     /// 次のトークンを読んで返却する
-    fn next(&mut self) -> Option<Token> {
+    fn next(&mut self) -> Result<Option<Token>, ParseError> {
         while self.pos < self.input.len() {
             match self.input[self.pos] {
-                ' ' => {
+                c if is_whitespace(c) => {
                     self.pos += 1;
                 }
                 ',' => {
                     self.pos += 1;
-                    return Some(Token::COMMA);
+                    return Ok(Some(Token::COMMA));
                 }
                 '+' => {
                     self.pos += 1;
-                    return Some(Token::PLUS);
+                    return Ok(Some(Token::PLUS));
                 }
                 '-' => {
-                    // TODO 負の数を扱えるようにしたい
                     self.pos += 1;
-                    return Some(Token::MINUS);
+                    return Ok(Some(Token::MINUS));
                 }
                 '*' => {
                     self.pos += 1;
-                    return Some(Token::STAR);
+                    return Ok(Some(Token::STAR));
                 }
                 '/' => {
                     self.pos += 1;
-                    return Some(Token::SLASH);
+                    return Ok(Some(Token::SLASH));
+                }
+                '%' => {
+                    self.pos += 1;
+                    return Ok(Some(Token::PERCENT));
+                }
+                '^' => {
+                    self.pos += 1;
+                    return Ok(Some(Token::CARET));
+                }
+                '&' => {
+                    self.pos += 1;
+                    return Ok(Some(Token::AMP));
+                }
+                '|' => {
+                    self.pos += 1;
+                    return Ok(Some(Token::PIPE));
+                }
+                '~' => {
+                    self.pos += 1;
+                    return Ok(Some(Token::TILDE));
+                }
+                '!' => {
+                    self.pos += 1;
+                    return Ok(Some(Token::BANG));
+                }
+                '<' if self.input.get(self.pos + 1) == Some(&'<') => {
+                    self.pos += 2;
+                    return Ok(Some(Token::SHL));
+                }
+                '>' if self.input.get(self.pos + 1) == Some(&'>') => {
+                    self.pos += 2;
+                    return Ok(Some(Token::SHR));
                 }
                 '(' => {
                     self.pos += 1;
-                    return Some(Token::LPAR);
+                    return Ok(Some(Token::LPAR));
                 }
                 ')' => {
                     self.pos += 1;
-                    return Some(Token::RPAR);
+                    return Ok(Some(Token::RPAR));
                 }
                 '{' => {
                     self.pos += 1;
-                    return Some(Token::LBRACE);
+                    return Ok(Some(Token::LBRACE));
                 }
                 '}' => {
                     self.pos += 1;
-                    return Some(Token::RBRACE);
+                    return Ok(Some(Token::RBRACE));
                 }
                 '=' => {
                     self.pos += 1;
-                    return Some(Token::EQ);
+                    return Ok(Some(Token::EQ));
                 }
                 ';' => {
                     self.pos += 1;
-                    return Some(Token::SEMICOLON);
+                    return Ok(Some(Token::SEMICOLON));
                 }
                 '0'..='9' => {
                     let start_idx = self.pos;
@@ -73,20 +117,52 @@ impl Scanner {
                             _ => break,
                         }
                     }
-                    let num: i32 = self.input[start_idx..self.pos]
+                    // 小数部
+                    let int_end = self.pos;
+                    if self.peek_is('.') {
+                        self.pos += 1;
+                        while self.pos < self.input.len() {
+                            match self.input[self.pos] {
+                                '0'..='9' => self.pos += 1,
+                                _ => break,
+                            }
+                        }
+                    }
+                    let digits: String = self.input[start_idx..self.pos]
                         .iter()
-                        .collect::<String>()
-                        .parse()
-                        .unwrap();
-                    return Some(Token::NUMBER(num));
+                        .filter(|c| **c != '.')
+                        .collect();
+                    let num: BigInt = digits.parse().unwrap();
+                    let scale = self.pos.saturating_sub(int_end + 1) as u32;
+
+                    // 末尾の d は固定小数点数 (例: 12.50d)。i と同じく識別子が続く場合は除く
+                    // d や i の 1 文字だけが続く場合は変数 d, i があってもリテラルになる (2d * x と書く)
+                    if self.peek_suffix('d') {
+                        self.pos += 1;
+                        return Ok(Some(Token::NUMBER(Value::Decimal { unscaled: num, scale })));
+                    }
+                    // 末尾の i は虚数 (例: 2i)。i で始まる識別子 (2if, 2i2 など) が続く場合は除く
+                    if self.peek_suffix('i') {
+                        let im: String = self.input[start_idx..self.pos].iter().collect();
+                        self.pos += 1;
+                        return Ok(Some(Token::NUMBER(Value::from_complex(0.0, im.parse().unwrap()))));
+                    }
+                    // 小数のリテラルには d を付ける必要がある
+                    if self.pos != int_end {
+                        let literal: String = self.input[start_idx..self.pos].iter().collect();
+                        return Err(ParseError::InvalidNumber(literal));
+                    }
+                    // i32 に収まらないリテラルは多倍長整数にする
+                    return Ok(Some(Token::NUMBER(Value::from_bigint(num))));
                 }
 
-                _ => {
+                // 識別子は英小文字か _ で始まり、2 文字目からは数字も使える (例: _1)
+                'a'..='z' | '_' => {
                     let start_idx = self.pos;
                     // 文字が続く限り次を読む
                     while self.pos < self.input.len() {
                         match self.input[self.pos] {
-                            'a'..='z' => self.pos += 1,
+                            'a'..='z' | '_' | '0'..='9' => self.pos += 1,
                             _ => break,
                         }
                     }
@@ -97,27 +173,71 @@ impl Scanner {
                         .unwrap();
 
                     // 予約語
-                    return match &*_word {
-                        "if" => Some(Token::IF),
-                        "else" => Some(Token::ELSE),
-                        "fn" => Some(Token::FN), 
-                        "return" => Some(Token::RETURN), 
-                        _ => Some(Token::IDENT(_word)),
-                    };
+                    return Ok(Some(match KEYWORDS.iter().find(|(word, _)| *word == _word) {
+                        Some((_, token)) => token.clone(),
+                        None => Token::IDENT(_word),
+                    }));
                 }
+                c => return Err(ParseError::InvalidCharacter(c)),
             }
         }
-        return None;
+        return Ok(None);
+    }
+
+    // 現在位置の文字が c か
+    fn peek_is(&self, c: char) -> bool {
+        self.pos < self.input.len() && self.input[self.pos] == c
+    }
+
+    // 現在位置の文字が数値の接尾辞 c で、その後に識別子の文字が続かないか
+    fn peek_suffix(&self, c: char) -> bool {
+        self.peek_is(c) && !matches!(self.input.get(self.pos + 1), Some('a'..='z' | '_' | '0'..='9'))
+    }
+}
+
+// トークンの区切りとして読み飛ばす文字か
+fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r')
+}
+
+/// 各トークンと、それが入力のどこにあるか (バイト単位の範囲) を返す。入力中の色付けに使う
+/// 読めない文字や数値は Err にして、その後ろも続けて読む
+pub fn spans(s: &str) -> Vec<(Result<Token, ParseError>, Range<usize>)> {
+    let mut result = Vec::new();
+    // 文字単位の位置からバイト単位の位置への対応
+    let mut offsets: Vec<usize> = s.char_indices().map(|(i, _)| i).collect();
+    offsets.push(s.len());
+
+    let mut scanner = Scanner::new(s);
+    loop {
+        // 空白を読み飛ばしてトークンの先頭を求める
+        while scanner.pos < scanner.input.len() && is_whitespace(scanner.input[scanner.pos]) {
+            scanner.pos += 1;
+        }
+        let start = scanner.pos;
+        let token = match scanner.next() {
+            Ok(Some(token)) => Ok(token),
+            Ok(None) => break,
+            Err(e) => {
+                // 読めない文字はその 1 文字だけを飛ばす
+                if scanner.pos == start {
+                    scanner.pos += 1;
+                }
+                Err(e)
+            }
+        };
+        result.push((token, offsets[start]..offsets[scanner.pos]));
     }
+    result
 }
 
-pub fn scanner(s: &str) -> Vec<Token> {
+pub fn scanner(s: &str) -> Result<Vec<Token>, ParseError> {
     let mut result = Vec::new();
 
     let mut scanner = Scanner::new(s);
-    while let Some(token) = scanner.next() {
+    while let Some(token) = scanner.next()? {
         result.push(token);
     }
 
-    result
+    Ok(result)
 }
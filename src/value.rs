@@ -0,0 +1,174 @@
+use crate::Rounding;
+use crate::Value;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Neg;
+
+impl Value {
+    /// 整数を値にする。i32 に収まれば Int、収まらなければ Big にする
+    pub fn from_bigint(n: BigInt) -> Value {
+        match n.to_i32() {
+            Some(i) => Value::Int(i),
+            None => Value::Big(n),
+        }
+    }
+
+    /// 整数として取り出す。有理数は 0 方向に切り捨てる
+    pub fn to_bigint(&self) -> BigInt {
+        match self {
+            Value::Int(i) => BigInt::from(*i),
+            Value::Big(n) => n.clone(),
+            Value::Rational(r) => r.to_integer(),
+            Value::Decimal { .. } => self.to_rational().to_integer(),
+            Value::Float(f) => BigInt::from(*f as i64),
+            Value::Complex { re, .. } => BigInt::from(*re as i64),
+            Value::Unit => BigInt::zero(),
+        }
+    }
+
+    pub fn to_rational(&self) -> BigRational {
+        match self {
+            Value::Rational(r) => r.clone(),
+            Value::Decimal { unscaled, scale } => BigRational::new(unscaled.clone(), BigInt::from(10).pow(*scale)),
+            Value::Float(f) | Value::Complex { re: f, .. } => BigRational::from_float(*f).unwrap_or_default(),
+            _ => BigRational::from_integer(self.to_bigint()),
+        }
+    }
+
+    /// 複素数を値にする。虚部が 0 なら Float にする
+    pub fn from_complex(re: f64, im: f64) -> Value {
+        if im == 0.0 {
+            Value::Float(re)
+        } else {
+            Value::Complex { re, im }
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Value::Int(i) => *i as f64,
+            Value::Float(f) | Value::Complex { re: f, .. } => *f,
+            _ => self.to_rational().to_f64().unwrap_or(f64::NAN),
+        }
+    }
+
+    /// 実部と虚部の組にする
+    pub fn to_complex(&self) -> (f64, f64) {
+        match self {
+            Value::Complex { re, im } => (*re, *im),
+            _ => (self.to_f64(), 0.0),
+        }
+    }
+
+    /// 固定小数点数の小数点以下の桁数。それ以外は 0
+    pub fn scale(&self) -> u32 {
+        match self {
+            Value::Decimal { scale, .. } => *scale,
+            _ => 0,
+        }
+    }
+
+    /// 小数点以下 scale 桁の固定小数点数にする
+    pub fn to_decimal(&self, scale: u32, rounding: Rounding) -> Value {
+        let scaled = self.to_rational() * BigRational::from_integer(BigInt::from(10).pow(scale));
+        let unscaled = match rounding {
+            Rounding::Truncate => scaled.trunc(),
+            // round は 0 から遠い方に丸める
+            Rounding::HalfUp => scaled.round(),
+            Rounding::HalfEven => {
+                let trunc = scaled.trunc();
+                let half = BigRational::new(BigInt::from(1), BigInt::from(2));
+                let away = &trunc + BigRational::from_integer(scaled.signum().to_integer());
+                match (&scaled - &trunc).abs().cmp(&half) {
+                    Ordering::Less => trunc,
+                    Ordering::Greater => away,
+                    Ordering::Equal if (trunc.to_integer() % BigInt::from(2)).is_zero() => trunc,
+                    Ordering::Equal => away,
+                }
+            }
+        };
+        Value::Decimal { unscaled: unscaled.to_integer(), scale }
+    }
+
+    /// 小数点以下 digits 桁に四捨五入した 10 進表記にする
+    pub fn to_decimal_string(&self, digits: usize) -> String {
+        self.to_decimal(digits as u32, Rounding::HalfUp).to_string()
+    }
+
+    /// if の条件として真になるか
+    pub fn is_positive(&self) -> bool {
+        match self {
+            Value::Int(i) => *i > 0,
+            Value::Big(n) => n.is_positive(),
+            Value::Rational(r) => r.is_positive(),
+            Value::Decimal { unscaled, .. } => unscaled.is_positive(),
+            Value::Float(f) => *f > 0.0,
+            // 大小のない複素数は実数として正の場合だけ真にする
+            Value::Complex { re, im } => *im == 0.0 && *re > 0.0,
+            Value::Unit => false,
+        }
+    }
+}
+
+// 10^digits 倍された整数を小数点付きの文字列にする
+fn format_scaled(scaled: &BigInt, digits: usize) -> String {
+    let sign = if scaled.is_negative() { "-" } else { "" };
+    let abs = scaled.abs().to_string();
+    if digits == 0 {
+        return format!("{}{}", sign, abs);
+    }
+    let abs = format!("{:0>width$}", abs, width = digits + 1);
+    let (int_part, frac_part) = abs.split_at(abs.len() - digits);
+    format!("{}{}.{}", sign, int_part, frac_part)
+}
+
+impl From<i32> for Value {
+    fn from(i: i32) -> Self {
+        Value::Int(i)
+    }
+}
+
+impl PartialEq<i32> for Value {
+    fn eq(&self, other: &i32) -> bool {
+        matches!(self, Value::Int(i) if *i == *other)
+    }
+}
+
+impl Neg for Value {
+    type Output = Value;
+
+    fn neg(self) -> Value {
+        match self {
+            Value::Int(i) => match i.checked_neg() {
+                Some(n) => Value::Int(n),
+                None => Value::from_bigint(-BigInt::from(i)),
+            },
+            Value::Big(n) => Value::from_bigint(-n),
+            Value::Rational(r) => Value::Rational(-r),
+            Value::Decimal { unscaled, scale } => Value::Decimal { unscaled: -unscaled, scale },
+            Value::Float(f) => Value::Float(-f),
+            Value::Complex { re, im } => Value::Complex { re: -re, im: -im },
+            Value::Unit => Value::Unit,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Big(n) => write!(f, "{}", n),
+            Value::Rational(r) => write!(f, "{}/{}", r.numer(), r.denom()),
+            // 末尾の 0 も scale の桁数まで残す
+            Value::Decimal { unscaled, scale } => write!(f, "{}", format_scaled(unscaled, *scale as usize)),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Complex { re, im } if *re == 0.0 => write!(f, "{}i", im),
+            Value::Complex { re, im } if im.is_sign_negative() => write!(f, "{}-{}i", re, -im),
+            Value::Complex { re, im } => write!(f, "{}+{}i", re, im),
+            Value::Unit => write!(f, "()"),
+        }
+    }
+}